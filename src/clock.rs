@@ -0,0 +1,47 @@
+//! monotonic clock for game time
+//! reads the CPU's time stamp counter, which is calibrated against the firmware's stall,
+//! so game time doesn't depend on how often the game loop manages to poll
+
+use core::time::Duration;
+use uefi::prelude::*;
+
+pub struct Clock{
+    ticks_per_us: u64,
+    last: u64, //counter at the last lap
+}
+
+impl Clock{
+    /// how long calibration stalls for in µs
+    const CALIBRATION: usize = 10_000;
+
+    /// measures the counter's speed, takes [Self::CALIBRATION] µs
+    pub fn new(st: &SystemTable<Boot>) -> Self{
+        let start = Self::counter();
+        st.boot_services().stall(Self::CALIBRATION);
+        let ticks_per_us = ((Self::counter() - start) / Self::CALIBRATION as u64).max(1);
+        info!("TSC ticks per us {}",ticks_per_us);
+
+        Self{
+            ticks_per_us,
+            last: Self::counter(),
+        }
+    }
+
+    fn counter() -> u64{
+        //the TSC is invariant on anything recent enough to boot UEFI
+        unsafe { core::arch::x86_64::_rdtsc() }
+    }
+
+    /// time since the last lap or [Self::reset]
+    pub fn lap(&mut self) -> Duration{
+        let now = Self::counter();
+        let ticks = now.saturating_sub(self.last);
+        self.last = now;
+        Duration::from_micros(ticks / self.ticks_per_us)
+    }
+
+    /// starts the next lap now, time spent paused or in menus isn't counted
+    pub fn reset(&mut self){
+        self.last = Self::counter();
+    }
+}
@@ -1,101 +1,144 @@
 use uefi::Status;
 use uefi_things::glib::{Sprite, GraphicsHandle};
-use  core::fmt::Debug;
 use uefi_things::fs::GetFileStatus;
 use alloc::fmt::Write;
 use alloc::vec::Vec;
+use alloc::string::String;
+use uefi::proto::console::text::Output;
+use tetros_core::{Board, BlockColour, Config, Event, Game, Mode, PieceKind, Tetromino};
+use alloc::collections::VecDeque;
 
 pub const BLOCK_SIZE: usize = 30; //block should always be square
-pub const BOARD_WIDTH: usize = BLOCK_SIZE * Board::GAME_WIDTH;
-pub const BOARD_HEIGHT: usize = BLOCK_SIZE * Board::GAME_HEIGHT;
+/// width of side panels in blocks, fits any tetromino
+const PANEL_BLOCKS: usize = 4;
+/// height in blocks given to each piece in a side panel
+const SLOT_BLOCKS: usize = 3;
+
+/// size in blocks of the board, its border and a panel each side
+/// `height` is visible rows only
+fn layout_blocks(width: usize, height: usize, preview: usize) -> (usize,usize){
+    let width = width + (2 * (PANEL_BLOCKS + 2));
+    let height = height.max(preview * SLOT_BLOCKS) + 2;
+    (width,height)
+}
+
+/// minimum resolution needed to play a game with `config`
+pub fn screen_size(config: &Config) -> (usize,usize){
+    let width = config.width.clamp(Game::MIN_WIDTH,Board::MAX_WIDTH);
+    let height = config.height.max(Game::MIN_HEIGHT);
+    let preview = config.preview.clamp(1,Game::MAX_PREVIEW);
+    let (width,height) = layout_blocks(width,height,preview);
+    (width * BLOCK_SIZE, height * BLOCK_SIZE)
+}
 
 
 
-pub struct Board{
+/// draws a [tetros_core::Game] to the screen
+pub struct BoardView{
     //location on screen
     location: (usize,usize),
-    //size in blocks
+    //size in blocks, only visible rows are drawn
     width: usize,
     height: usize,
+    hidden: usize, //board rows above the first drawn one
 
-    contents: Vec<BlockColour>, //contains block colours within game grid
+    drawn: Vec<Cell>, //cells currently rendered to sprite
     blocks: Vec<Block>, //contains block data
+    ghosts: Vec<Block>, //faded blocks for the ghost piece
     sprite: Sprite,
+    ghost: bool, //draw the ghost piece
+
+    hold: PieceBox, //left of the board
+    preview: PieceBox, //right of the board
 }
 
-impl Board{
+impl BoardView{
 
-    const GAME_HEIGHT: usize = 18;
-    const GAME_WIDTH: usize = 10;
-    pub fn new(st: &uefi::prelude::SystemTable<uefi::prelude::Boot>, g: &uefi_things::glib::GraphicsHandle) -> Self{
-        let location = {
-            let (mut width,mut height) = g.get_resolution();
-            //get co-ords of board
-            width  -= BOARD_WIDTH;
-            height -= BOARD_HEIGHT;
+    pub fn new(st: &uefi::prelude::SystemTable<uefi::prelude::Boot>, g: &uefi_things::glib::GraphicsHandle, game: &Game) -> Self{
+        let board = game.board();
+        let width = board.width();
+        let height = board.visible_height();
+        let hidden = board.hidden();
 
-            width /= 2;
-            height /= 2;
-
-            (width,height)
+        let location = {
+            let (x,y) = g.get_resolution();
+            //panels are the same width so the board is centred,
+            //the preview may be taller than the board so the top is taken from the whole layout
+            let (_,layout) = layout_blocks(width,height,game.preview());
+            let x = x.saturating_sub(width * BLOCK_SIZE) / 2;
+            let y = (y.saturating_sub(layout * BLOCK_SIZE) / 2) + BLOCK_SIZE;
+
+            (x,y)
         };
-        let width = Board::GAME_WIDTH;
-        let height = Board::GAME_HEIGHT;
 
-        let mut contents = Vec::new();
-        contents.resize(width*height,BlockColour::None);
+        let mut drawn = Vec::new();
+        drawn.resize(width*height,Cell::Block(BlockColour::None));
 
         let mut blocks = Vec::new();
+        let mut ghosts = Vec::new();
 
         {
             let fs = uefi_things::proto::get_proto::<uefi::proto::media::fs::SimpleFileSystem>(st.boot_services()).unwrap().unwrap();
 
-            //loop pls
-            blocks.push(BlockColour::Red.get_as_sprite(fs).unwrap().unwrap());
-            blocks.push(BlockColour::Blue.get_as_sprite(fs).unwrap().unwrap());
-            blocks.push(BlockColour::Green.get_as_sprite(fs).unwrap().unwrap());
-            blocks.push(BlockColour::Cyan.get_as_sprite(fs).unwrap().unwrap());
-            blocks.push(BlockColour::Grey.get_as_sprite(fs).unwrap().unwrap());
-            blocks.push(BlockColour::Yellow.get_as_sprite(fs).unwrap().unwrap());
-            blocks.push(BlockColour::Orange.get_as_sprite(fs).unwrap().unwrap());
-            blocks.push(BlockColour::Purple.get_as_sprite(fs).unwrap().unwrap());
-            blocks.push(BlockColour::None.get_as_sprite(fs).unwrap().unwrap());
+            for colour in COLOURS{
+                blocks.push(colour.get_as_sprite(fs).unwrap().unwrap());
+                ghosts.push(colour.get_as_ghost(fs).unwrap().unwrap());
+            }
 
         }
 
-        let sprite = Sprite::new(width*BLOCK_SIZE,height*BLOCK_SIZE);
+        let mut sprite = Sprite::new(width*BLOCK_SIZE,height*BLOCK_SIZE);
 
+        //sprite starts out blank, make sure it matches `drawn`
+        {
+            let empty = blocks.iter().find(|b| b.colour == BlockColour::None).unwrap();
+            for y in 0..height{
+                for x in 0..width{
+                    sprite.render_sprite(empty,(x*BLOCK_SIZE,y*BLOCK_SIZE));
+                }
+            }
+        }
 
+        //one block for the board border then the panel
+        let hold = PieceBox::new((location.0.saturating_sub(BLOCK_SIZE * (PANEL_BLOCKS + 1)), location.1), 1);
+        let preview = PieceBox::new((location.0 + (BLOCK_SIZE * (width + 1)), location.1), game.preview());
 
         return Self{
             location,
             width,
             height,
-            contents,
+            hidden,
+            drawn,
             blocks,
+            ghosts,
             sprite,
+            ghost: true,
+            hold,
+            preview,
         }
     }
 
     fn get_block(&self, colour: BlockColour) -> Option<&Block>{
-        for block in &self.blocks{
-            if block.colour == colour{
-                return Some(block)
-            }
-        }
-        None
+        find_block(&self.blocks,colour)
     }
     pub fn render_bg(&self, sprite: &mut Sprite) {
+        self.render_border(sprite,self.location,(self.width,self.height));
+        self.render_border(sprite,self.hold.location,self.hold.size());
+        self.render_border(sprite,self.preview.location,self.preview.size());
+    }
+
+    /// fills the area one block around `size` blocks at `location` with grey blocks
+    fn render_border(&self, sprite: &mut Sprite, location: (usize,usize), size: (usize,usize)) {
         let block = self.get_block(BlockColour::Grey).expect("unable to find grey block");
-        let (mut start_x,mut start_y) = self.location;
+        let (mut start_x,mut start_y) = location;
         //one block top right of board
-        start_x -= BLOCK_SIZE;
-        start_y -= BLOCK_SIZE;
+        start_x = start_x.saturating_sub(BLOCK_SIZE);
+        start_y = start_y.saturating_sub(BLOCK_SIZE);
 
         let mut count = 0;
-        for row in 0..self.height + 2{
+        for row in 0..size.1 + 2{
             let y = start_y + (BLOCK_SIZE * row);
-            for col in 0..self.width + 2{
+            for col in 0..size.0 + 2{
                 count += 1;
 
                 let x = start_x + (BLOCK_SIZE * col);
@@ -105,128 +148,96 @@ impl Board{
         }
         info!("blocks drawn: {}",count);
     }
-    pub fn draw(&self,g: &mut GraphicsHandle,) -> uefi::Result{
-        use uefi::proto::console::gop;
-        g.gop.blt(gop::BltOp::BufferToVideo {
-            buffer: &self.sprite,
-            src: gop::BltRegion::Full,
-            dest: self.location,
-            dims: self.resolution()
-        })
-    }
 
-    pub fn update_block(&mut self,location: (usize,usize)){
-        let (x,y) = location;
-        let address = (y * self.width) + x;
-        let colour = self.contents[address];
-        let block = self.get_block(colour).unwrap().clone();
-
-        self.sprite.render_sprite(&block,(x*BLOCK_SIZE,y*BLOCK_SIZE));
-
-    }
-
-    pub fn set_and_update(&mut self,location: (usize,usize),colour: BlockColour){
-        self.set(location,colour);
-        self.update_block(location);
+    /// turns the ghost piece on or off, shown from the next [Self::update]
+    pub fn set_ghost(&mut self, ghost: bool){
+        self.ghost = ghost;
     }
 
-    pub fn set(&mut self, at:(usize, usize), colour: BlockColour){
-        //if out of bounds
-        if (at.0 > self.width) || (at.1 > self.height){ return }
-
-        let (x,y) = at;
-        let address = (y * self.width) + x;
-
-        self.contents[address] = colour;
-
-
+    /// location and size in pixels of the board
+    pub fn area(&self) -> ((usize,usize),(usize,usize)){
+        (self.location,(self.width * BLOCK_SIZE,self.height * BLOCK_SIZE))
     }
 
-    fn is_free(&self,coords: (usize,usize)) -> bool{
-        let (x,y) = coords;
-        //info!("({} * {}) + {} = {}",y,self.width,x,(y*self.width ) + x);
-        let index = (y * self.width) + x;
-
-        if x > self.width  { return false }
-        if y > self.height { return false }
-
-        return if self.contents[index] == BlockColour::None {
-            true
-        } else {
-            false
+    /// blanks the board and side panels so a paused game can't be studied
+    /// [Self::draw] shows them again
+    pub fn cover(&mut self, g: &mut GraphicsHandle) -> uefi::Result{
+        let (x,y) = self.sprite.resolution();
+        blt_sprite(g,&Sprite::new(x,y),self.location)?.unwrap();
+        for panel in [&mut self.hold,&mut self.preview]{
+            let (x,y) = panel.sprite.resolution();
+            blt_sprite(g,&Sprite::new(x,y),panel.location)?.unwrap();
+            panel.dirty = true;
         }
+        Ok(Status::SUCCESS.into())
     }
 
-    fn index(&self,coords:(usize,usize)) -> usize {
-        (coords.1 * self.width) + coords.0
+    /// top left pixel of the space under the hold box, used for the [Hud]
+    pub fn hud_location(&self) -> (usize,usize){
+        let (x,y) = self.hold.location;
+        (x, y + (BLOCK_SIZE * (self.hold.size().1 + 2)))
     }
 
-    /// checks rows top..bottom returns row of first full row
-    fn find_row(&self) -> Option<usize>{
-        //iterates through rows
-        'row: for row in  0..self.height {
+    /// draws the board, side panels are only drawn when they have changed
+    pub fn draw(&mut self,g: &mut GraphicsHandle,) -> uefi::Result{
+        self.hold.draw(g)?.unwrap();
+        self.preview.draw(g)?.unwrap();
+        blt_sprite(g,&self.sprite,self.location)
+    }
 
-            for block in 0..self.width {
-                //checks for BlockColour::None
-                //if found row is not full so it continues to the next row
-                if let BlockColour::None = self.contents[self.index((block,row))] {
-                    continue 'row;
-                }
+    /// re-renders every block that changed since the last update
+    /// the falling tetromino and its ghost are drawn over the board
+    pub fn update(&mut self, game: &Game){
+        let board = game.board();
+        let mut frame = Vec::with_capacity(self.width*self.height);
+        for y in 0..self.height{
+            for x in 0..self.width{
+                frame.push(Cell::Block(board.get((x,y + self.hidden))));
             }
-            //this is only reached when the above loop completes
-            //it should only complete if the row is full
-            return Some(row);
         }
-        None
-    }
 
-    /// clears given row
-    fn clear_row(&mut self, row: usize){
-        for i in 0..self.width{
-            self.set_and_update((i,row),BlockColour::None);
+        //ghost first so the falling piece is drawn over it when they overlap
+        if let Some(tet) = game.ghost().filter(|_| self.ghost){
+            self.overlay(&mut frame,&tet,Cell::Ghost(tet.colour));
+        }
+        if let Some(tet) = game.active(){
+            self.overlay(&mut frame,tet,Cell::Block(tet.colour));
         }
-    }
 
-    ///scrolls all blocks above `row` down
-    fn scroll_from(&mut self, row: usize){
-        for r in (1..row+1).rev(){
-            for block in 0..self.width{
-                let state = self.contents[self.index((block,r-1))];
-                self.set_and_update((block,r) ,state);
+        for i in 0..frame.len(){
+            if frame[i] != self.drawn[i]{
+                self.update_block((i % self.width, i / self.width), frame[i]);
             }
         }
-        self.clear_row(0);
-    }
 
-    ///clears full rows and scrolls blocks down
-    /// returns number of rows cleared
-    pub fn clean_screen(&mut self) -> i64{ //return type subject to change
-        let mut cleared = 0;
+        self.hold.show(game.held().into_iter(),&self.blocks);
+        self.preview.show(game.queue().iter().take(game.preview()).copied(),&self.blocks);
+    }
 
-        loop {
-            match self.find_row(){
-                None => break,
-                Some( row ) => {
-                    cleared += 1;
-                    self.clear_row(row);
-                    self.scroll_from(row);
-                }
-            }
+    /// sets every cell of `frame` covered by `tet` to `cell`
+    fn overlay(&self, frame: &mut [Cell], tet: &Tetromino, cell: Cell){
+        for (x,y) in tet.blocks(){
+            let y = y - self.hidden as isize;
+            if x.is_negative() || y.is_negative() { continue }
+            let (x,y) = (x as usize, y as usize);
+            if (x >= self.width) || (y >= self.height) { continue }
+            frame[(y * self.width) + x] = cell;
         }
-        return cleared
     }
 
-    pub fn is_lost(&self) -> bool{
-        for block in 0..self.width{
-            if !self.is_free((block,0)){
-                return true
-            }
-        }
-        false
+    fn update_block(&mut self,location: (usize,usize), cell: Cell){
+        let (x,y) = location;
+        let block = match cell {
+            Cell::Block(colour) => self.get_block(colour),
+            Cell::Ghost(colour) => find_block(&self.ghosts,colour),
+        }.unwrap().clone();
+
+        self.sprite.render_sprite(&block,(x*BLOCK_SIZE,y*BLOCK_SIZE));
+        self.drawn[(y * self.width) + x] = cell;
     }
 }
 
-impl core::ops::Deref for Board {
+impl core::ops::Deref for BoardView {
     type Target = Sprite;
 
     fn deref(&self) -> &Self::Target {
@@ -248,348 +259,352 @@ impl core::ops::Deref for Block{
     }
 }
 
-#[derive(Debug,Clone,Copy,PartialEq)]
-pub enum BlockColour{
-    Red,
-    Blue,
-    Green,
-    Cyan,
-    Grey,
-    Yellow,
-    Orange,
-    Purple,
-    None,
+/// replaces the whole screen with the background for `view`
+/// should be the only full screen draw while a game is played
+pub fn draw_background(g: &mut GraphicsHandle, view: &BoardView) -> uefi::Result{
+    let (width,height) = g.get_resolution();
+    let buff = g.mut_get_buff(0).unwrap();
+    *buff = Sprite::new(width,height);
+    view.render_bg(buff);
+    g.draw(0)
 }
 
-impl alloc::fmt::Display for BlockColour {
-    fn fmt(&self, f: &mut alloc::fmt::Formatter) -> alloc::fmt::Result {
-        write!(f, "{:?}", self)
-    }
+/// glyph size used by the EDK2 graphics console
+pub const GLYPH: (usize,usize) = (8,19);
+
+/// first text cell at or after the pixel `location`
+pub fn text_cell(o: &mut Output, g: &GraphicsHandle, location: (usize,usize)) -> (usize,usize){
+    let (width,height) = g.get_resolution();
+    let (columns,rows) = match o.current_mode().unwrap().unwrap() {
+        Some(mode) => (mode.columns(),mode.rows()),
+        None => (width / GLYPH.0, height / GLYPH.1),
+    };
+
+    //the console centres its text on the screen
+    let offset_x = width.saturating_sub(columns * GLYPH.0) / 2;
+    let offset_y = height.saturating_sub(rows * GLYPH.1) / 2;
+    let column = (location.0.saturating_sub(offset_x) + GLYPH.0 - 1) / GLYPH.0;
+    let row = (location.1.saturating_sub(offset_y) + GLYPH.1 - 1) / GLYPH.1;
+    (column,row)
 }
 
-impl BlockColour{
-    const BLOCK_PATH: &'static str = "/tetros/blocks/";
-    pub fn get_as_sprite(self,fs: &mut uefi::proto::media::fs::SimpleFileSystem) -> uefi::Result<Block>{
-        use uefi::proto::media::file::{FileMode, FileAttribute};
-        use uefi::proto::media::file::FileType;
-        // if none create blank sprite
-        /*if let None = self{
-
-            let mut data = Vec::new();
-            data.resize(BLOCK_SIZE*BLOCK_SIZE,BltPixel::new(0,0,0));
-
-            let mut s = Sprite::new(BLOCK_SIZE,BLOCK_SIZE);
-            *s = *data;
-
-            return Ok(uefi::Completion::new(Status::SUCCESS,
-            Block{
-                colour: self,
-                sprite: s
-            }))
-        }*/
-
-        let mut f_name = alloc::string::String::new();
-        write!(f_name,"{}{:?}.ppm",Self::BLOCK_PATH,self).unwrap();
-
-        let ppm_file = match uefi_things::fs::get_file_from_path(fs,&f_name,FileMode::Read,FileAttribute::empty()){
-            GetFileStatus::Found(f) => f.into_type().unwrap().unwrap(),
-            GetFileStatus::NotFound(_) => {
-
-                let s = Sprite::new(BLOCK_SIZE,BLOCK_SIZE);
-                return Ok(uefi::Completion::new(Status::SUCCESS,
-                                                Block{
-                                                    colour: self,
-                                                    sprite: s
-                                                }))
-            },
-            GetFileStatus::Err(e) => return Err(e.into()),
-        };
+/// text beside the board drawn with the text console
+/// lines are only rewritten when they change
+pub struct Hud{
+    position: (usize,usize), //column and row of the first line
+    shown: Vec<String>,
+    event: Option<Event>, //last thing worth telling the player, kept until the next one
+    best: Option<String>, //personal best of the mode being played
+}
 
-        let raw_ppm = match ppm_file{
-            FileType::Regular(f) => {
-                uefi_things::fs::read_file(f).unwrap().unwrap()
-            }
-            FileType::Dir(_) => {
-                return Err(Status::LOAD_ERROR.into())
-            }
-        };
-        let mut sprite = Sprite::new(BLOCK_SIZE,BLOCK_SIZE);
-        if let Err(_) = sprite.read_ppm(&raw_ppm){
-            return Err(Status::LOAD_ERROR.into());
-        };
+impl Hud{
+    /// characters per line, fits under a side panel
+    const WIDTH: usize = (PANEL_BLOCKS * BLOCK_SIZE) / GLYPH.0;
+
+    /// `location` is in pixels and is converted into the nearest text cell
+    pub fn new(o: &mut Output, g: &GraphicsHandle, location: (usize,usize)) -> Self{
+        Self{
+            position: text_cell(o,g,location),
+            shown: Vec::new(),
+            event: None,
+            best: None,
+        }
+    }
 
-        let block = Block{
-            colour: self,
-            sprite
-        };
+    /// personal best shown above the stats, already formatted by the mode
+    pub fn set_best(&mut self, best: Option<String>){
+        self.best = best;
+    }
 
-        return Ok(uefi::Completion::new(Status::SUCCESS, block));
+    /// forgets the last event, for a new game
+    pub fn reset(&mut self){
+        self.event = None;
     }
-}
 
-pub struct Tetromino {
-    height: usize,
-    width: usize,
-    pub location: (isize,isize),
-    pub colour: BlockColour,
-    contents: Vec<bool>,
-}
+    /// keeps the newest of `events` to show under the stats
+    pub fn events(&mut self, events: Vec<Event>){
+        if let Some(event) = events.last() {
+            self.event = Some(*event);
+        }
+    }
 
-impl Tetromino{
-    pub const SQUARE: u16 = 0b1111;
-    pub const T_SHAPE: u16 = 0b010111000;
-    pub const L_SHAPE: u16 = 0b01001001011;
-    pub const L_SHAPE_R: u16 = 0b010010110;
-    pub const I_SHAPE: u16 = 0b1111;
-    pub const Z_SHAPE: u16 = 0b110011000;
-    pub const Z_SHAPE_R: u16 = 0b000011110;
-    /// layout as a binary representation of layout where bit order is layout order
-    /// excess bits will be ignores
-    pub fn new(size: (usize,usize) ,layout: u16, colour: BlockColour) -> Self{
-
-        let bt = |i: u16, test: u16| -> bool {
-            let mut bit = 1;
-            bit <<= i;
-
-            return if (bit & test) == 0 {
-                false
-            } else {
-                true
+    /// lines from [Mode::hud] come first, then the stats
+    pub fn draw(&mut self, o: &mut Output, game: &Game, mode: &dyn Mode) -> uefi::Result{
+        let mut lines = mode.hud(game);
+        if let Some(best) = &self.best {
+            lines.push(alloc::format!("PB {}",best));
+        }
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        lines.push(alloc::format!("Score {}",game.score()));
+        lines.push(alloc::format!("Level {}",game.level()));
+        lines.push(alloc::format!("Lines {}",game.lines()));
+        match game.combo() {
+            Some(combo) if combo > 0 => lines.push(alloc::format!("Combo {}",combo)),
+            _ => lines.push(String::new()),
+        }
+        if game.back_to_back() {
+            lines.push(String::from("Back-to-Back"));
+        } else {
+            lines.push(String::new());
+        }
+        if let Some(event) = self.event {
+            lines.push(String::new());
+            //wrap long events like "B2B Mini T-Spin Single" at spaces
+            let mut line = String::new();
+            for word in alloc::format!("{}",event).split(' ') {
+                if !line.is_empty() && (line.len() + 1 + word.len() > Self::WIDTH) {
+                    lines.push(core::mem::take(&mut line));
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(word);
             }
+            lines.push(line);
+        }
 
-        };
-
-        let (width,height) = size;
-        let mut contents = Vec::new();
-        contents.resize(width*height,false);
+        self.show(o,lines)
+    }
 
-        for i in 0..contents.len(){
-            contents[i] = bt(i as u16,layout);
+    fn show(&mut self, o: &mut Output, lines: Vec<String>) -> uefi::Result{
+        let (column,row) = self.position;
+        for (i,line) in lines.iter().enumerate(){
+            if self.shown.get(i) == Some(line) { continue }
+            o.set_cursor_position(column,row + i)?.unwrap();
+            write!(o,"{:<width$.width$}",line,width = Self::WIDTH).unwrap();
         }
-        //location out of bounds will just silent error
-        let location = (0,0);
-        return Self{
-            height,
-            width,
-            location,
-            colour,
-            contents
+        //blank lines that are no longer used
+        for i in lines.len()..self.shown.len(){
+            o.set_cursor_position(column,row + i)?.unwrap();
+            write!(o,"{:<width$}","",width = Self::WIDTH).unwrap();
         }
 
+        self.shown = lines;
+        Ok(Status::SUCCESS.into())
     }
+}
 
-    fn locate(&self,index: usize) -> (usize,usize){
-        let y = index / self.width;
-        let x = index % self.width;
-        (x,y)
-    }
-    fn index(&self,coords: (usize,usize)) -> usize{
-        let (x,y) = coords;
-        //info!("({} * {}) + {} = {}",y,self.width,x,(y*self.width ) + x);
-        (y * self.width) + x
-    }
+/// contents of a single cell on the board
+#[derive(Clone,Copy,PartialEq)]
+enum Cell{
+    Block(BlockColour),
+    Ghost(BlockColour),
+}
+
+/// every colour with a block sprite
+const COLOURS: [BlockColour;9] = [
+    BlockColour::Red,
+    BlockColour::Blue,
+    BlockColour::Green,
+    BlockColour::Cyan,
+    BlockColour::Grey,
+    BlockColour::Yellow,
+    BlockColour::Orange,
+    BlockColour::Purple,
+    BlockColour::None,
+];
+
+/// panel beside the board showing pieces stacked top to bottom
+struct PieceBox{
+    location: (usize,usize),
+    shown: VecDeque<PieceKind>,
+    dirty: bool, //set when sprite needs to be drawn again
+    sprite: Sprite,
+}
 
-    fn get_scan(&self, y: usize) -> Vec<bool>{
-        let mut scan = Vec::new();
-        scan.resize(self.width,false);
-        for block in 0..self.width{
-            scan[block] = self.contents[self.index((block,y))]
+impl PieceBox{
+    /// `slots` is the number of pieces that fit in the box
+    fn new(location: (usize,usize), slots: usize) -> Self{
+        Self{
+            location,
+            shown: VecDeque::new(),
+            dirty: true,
+            sprite: Sprite::new(PANEL_BLOCKS * BLOCK_SIZE, slots * SLOT_BLOCKS * BLOCK_SIZE),
         }
-        scan
     }
 
-    pub fn rotate_right(&mut self){
-        let mut scratch = Tetromino::new((self.height,self.width),0,self.colour);
+    /// size in blocks
+    fn size(&self) -> (usize,usize){
+        let (x,y) = self.sprite.resolution();
+        (x / BLOCK_SIZE, y / BLOCK_SIZE)
+    }
 
-        for scan in 0..self.height {
-            let scan_dat = self.get_scan(scan);
-            for block in 0..self.width {
+    /// re-renders the box only if `pieces` differ from what is already shown
+    fn show<I>(&mut self, pieces: I, blocks: &[Block])
+        where I: Iterator<Item = PieceKind> + Clone
+    {
+        if self.shown.iter().copied().eq(pieces.clone()) && !self.dirty { return }
 
-                let far = scratch.index(((scratch.width - 1) - scan,block));
-                scratch.contents[far] = scan_dat[block];
+        let (width,height) = self.size();
+        let empty = find_block(blocks,BlockColour::None).unwrap();
+        for y in 0..height{
+            for x in 0..width{
+                self.sprite.render_sprite(empty,(x*BLOCK_SIZE,y*BLOCK_SIZE));
             }
         }
 
-        self.width = scratch.width;
-        self.height = scratch.height;
-        self.contents = scratch.contents;
+        self.shown.clear();
+        for (slot,kind) in pieces.enumerate(){
+            if slot >= height / SLOT_BLOCKS { break }
+            let tet = Tetromino::from_kind(kind);
+            let block = find_block(blocks,tet.colour).unwrap();
+            for (x,y) in tet.blocks(){
+                if y as usize >= SLOT_BLOCKS { continue }
+                let y = (slot * SLOT_BLOCKS) + y as usize;
+                self.sprite.render_sprite(block,(x as usize * BLOCK_SIZE, y * BLOCK_SIZE));
+            }
+            self.shown.push_back(kind);
+        }
 
+        self.dirty = true;
     }
 
+    fn draw(&mut self, g: &mut GraphicsHandle) -> uefi::Result{
+        if !self.dirty { return Ok(Status::SUCCESS.into()) }
+        self.dirty = false;
+        blt_sprite(g,&self.sprite,self.location)
+    }
+}
 
-    pub fn rotate_left(&mut self){
-        let mut scratch = Tetromino::new((self.height,self.width),0,self.colour);
-
-        for scan in 0..self.height {
-            let scan_dat = self.get_scan(scan);
-            for block in 0..self.width {
-                //info!("from {:?} index {}",() )
-                let far = scratch.index((scan,(scratch.height - 1) - block));
-                scratch.contents[far] = scan_dat[block];
-
-            }
+fn find_block(blocks: &[Block], colour: BlockColour) -> Option<&Block>{
+    for block in blocks{
+        if block.colour == colour{
+            return Some(block)
         }
-
-        self.width = scratch.width;
-        self.height = scratch.height;
-        self.contents = scratch.contents;
     }
+    None
+}
 
-    pub fn set(&self, board: &mut Board){
-        for i in 0..self.contents.len(){
-            if self.contents[i] == false{
-                continue
-            }
-            let (x ,y) = self.locate(i);
-            let mut x = x as isize;
-            let mut y = y as isize;
+/// copies the whole of `sprite` to the screen at `location`
+fn blt_sprite(g: &mut GraphicsHandle, sprite: &Sprite, location: (usize,usize)) -> uefi::Result{
+    use uefi::proto::console::gop;
+    g.gop.blt(gop::BltOp::BufferToVideo {
+        buffer: sprite,
+        src: gop::BltRegion::Full,
+        dest: location,
+        dims: sprite.resolution()
+    })
+}
 
-            x += self.location.0;
-            y += self.location.1;
+/// loads the sprite used to draw a [BlockColour]
+pub trait BlockSprite{
+    fn get_as_sprite(self,fs: &mut uefi::proto::media::fs::SimpleFileSystem) -> uefi::Result<Block>;
+    /// faded copy of the block with its edge left solid, used for the ghost piece
+    fn get_as_ghost(self,fs: &mut uefi::proto::media::fs::SimpleFileSystem) -> uefi::Result<Block>;
+}
 
-            if x.is_negative() || y.is_negative() { continue }
+const BLOCK_PATH: &'static str = "/tetros/blocks/";
 
-            board.set_and_update((x as usize,y as usize),self.colour);
-        }
+impl BlockSprite for BlockColour{
+    fn get_as_sprite(self,fs: &mut uefi::proto::media::fs::SimpleFileSystem) -> uefi::Result<Block>{
+        load_block(self,fs,false)
     }
-    pub fn unset(&self, board: &mut Board){
-        for i in 0..self.contents.len(){
-            if !self.contents[i] { continue }
-            let (x  ,y) = self.locate(i);
-            let mut x = x as isize;
-            let mut y = y as isize;
-
-            x += self.location.0;
-            y += self.location.1;
-
-            if x.is_negative() || y.is_negative() {
-                continue
-            }
 
-            board.set_and_update((x as usize,y as usize),BlockColour::None);
-        }
-    }
-    pub fn do_and_update(&mut self,task: fn(&mut Self), board: &mut Board, g: &mut GraphicsHandle){
-        self.unset(board);
-        task(self);
-        self.set(board);
-        board.draw(g).unwrap().unwrap();
+    fn get_as_ghost(self,fs: &mut uefi::proto::media::fs::SimpleFileSystem) -> uefi::Result<Block>{
+        load_block(self,fs,true)
     }
+}
 
-    pub fn relocate(&mut self, to: (i8,i8)) -> Result<(),()> {
-        let (x,y) = to;
-
-        self.location.0 += x as isize;
-        self.location.1 += y as isize;
-
-        return Ok(())
-    }
+fn load_block(colour: BlockColour, fs: &mut uefi::proto::media::fs::SimpleFileSystem, ghost: bool) -> uefi::Result<Block>{
+    use uefi::proto::media::file::{FileMode, FileAttribute};
+    use uefi::proto::media::file::FileType;
+    // if none create blank sprite
+    /*if let None = self{
 
-    /// check for occupied spaces around tetromino
-    /// returns true if self can stay here
+        let mut data = Vec::new();
+        data.resize(BLOCK_SIZE*BLOCK_SIZE,BltPixel::new(0,0,0));
 
-    pub fn is_legal(&self, board: &Board) -> bool{
-        //check boundaries
-        for i in 0..self.contents.len(){
-            if self.contents[i] == false {continue}
+        let mut s = Sprite::new(BLOCK_SIZE,BLOCK_SIZE);
+        *s = *data;
 
-            let (x,y) = self.locate(i);
-            let mut x = x as isize;
-            let mut y = y as isize;
+        return Ok(uefi::Completion::new(Status::SUCCESS,
+        Block{
+            colour: self,
+            sprite: s
+        }))
+    }*/
 
-            //info!("x: {} + {}",x,self.location.0);
-            //info!("y: {} + {}",y,self.location.1);
+    let mut f_name = alloc::string::String::new();
+    write!(f_name,"{}{:?}.ppm",BLOCK_PATH,colour).unwrap();
 
-            x += self.location.0;
-            y += self.location.1;
+    let ppm_file = match uefi_things::fs::get_file_from_path(fs,&f_name,FileMode::Read,FileAttribute::empty()){
+        GetFileStatus::Found(f) => f.into_type().unwrap().unwrap(),
+        GetFileStatus::NotFound(_) => {
 
-            if x.is_negative() || y.is_negative(){
-                return false
-            }
-            if (x as usize >= board.width ) || (y as usize >= board.height) {
-                return false
-            }
-            if !board.is_free((x as usize,y as usize)){
-                return false
-            }
+            let s = Sprite::new(BLOCK_SIZE,BLOCK_SIZE);
+            return Ok(uefi::Completion::new(Status::SUCCESS,
+                                            Block{
+                                                colour,
+                                                sprite: s
+                                            }))
+        },
+        GetFileStatus::Err(e) => return Err(e.into()),
+    };
+
+    let mut raw_ppm = match ppm_file{
+        FileType::Regular(f) => {
+            uefi_things::fs::read_file(f).unwrap().unwrap()
+        }
+        FileType::Dir(_) => {
+            return Err(Status::LOAD_ERROR.into())
         }
-        true
+    };
+    if ghost && fade_ppm(&mut raw_ppm).is_none(){
+        return Err(Status::LOAD_ERROR.into());
     }
 
-    /// checks left and right of current tetromino for legal spaces
-    /// returns `(left,below,right)`
-    /// self must be unset beforehand
-    pub fn check_sides(&self, board: &Board) -> (bool,bool,bool) {
-        let mut test = Tetromino{
-            height: self.height,
-            width: self.height,
-            location: self.location,
-            colour: self.colour,
-            contents: self.contents.clone()
-        };
-
-        test.relocate((-1,0)).unwrap();
-        let left = test.is_legal(board);
-
-        test.relocate((2,0)).unwrap();
-        let right = test.is_legal(board);
-
-        test.relocate((-1,1)).unwrap();
-        let below = test.is_legal(board);
-
-        return (left,below,right)
+    let mut sprite = Sprite::new(BLOCK_SIZE,BLOCK_SIZE);
+    if let Err(_) = sprite.read_ppm(&raw_ppm){
+        return Err(Status::LOAD_ERROR.into());
+    };
 
-    }
-
-    pub fn legal_move(&mut self, to: (i8,i8),board: &mut Board) -> bool{
-        self.unset(board);
-        if let Err(_) = self.relocate(to) {
-            self.set(board);
-            return false
-        };
-        let mut ret = true;
-        if !self.is_legal(board){
-            let (x,y) = to;
-            self.relocate((-x,-y)).unwrap();
-            ret = false;
-        }
+    let block = Block{
+        colour,
+        sprite
+    };
 
+    return Ok(uefi::Completion::new(Status::SUCCESS, block));
+}
 
-        self.set(board);
-        ret
-    }
+/// darkens everything but a 2 pixel edge of a binary (P6) ppm in place
+/// returns `None` if `ppm` isn't a P6 with 8 bit channels
+fn fade_ppm(ppm: &mut [u8]) -> Option<()>{
+    const EDGE: usize = 2;
 
-    pub fn safe_ror(&mut self, board: &mut Board) -> bool{
-        self.unset(board);
-        self.rotate_right();
-        if !self.is_legal(board){
-            let (left,_,right) = self.check_sides(board);
-            if left { self.relocate((-1,0)).unwrap();
-            } else if right { self.relocate((1,0)).unwrap();
-            } else {
-                self.rotate_left();
-                self.set(board);
-                return false
+    // header is 4 whitespace separated fields, comments run to the end of the line
+    let mut fields = [0usize;4];
+    let mut pos = 0;
+    for field in fields.iter_mut(){
+        loop {
+            match ppm.get(pos)? {
+                b'#' => while *ppm.get(pos)? != b'\n' { pos += 1 },
+                c if c.is_ascii_whitespace() => pos += 1,
+                _ => break,
             }
         }
-        self.set(board);
-        true
+        let start = pos;
+        while !ppm.get(pos)?.is_ascii_whitespace() { pos += 1 }
+        *field = match &ppm[start..pos] {
+            b"P6" => 0,
+            n => core::str::from_utf8(n).ok()?.parse().ok()?,
+        };
     }
-    pub fn safe_rol(&mut self, board: &mut Board) -> bool{
-        self.unset(board);
-        self.rotate_left();
-        if !self.is_legal(board) {
-            let (left,_,right) = self.check_sides(board);
-            if left { self.relocate((-1,0)).unwrap();
-            } else if right { self.relocate((1,0)).unwrap();
-            } else {
-                self.rotate_left();
-                self.set(board);
-                return false
+    pos += 1; //single whitespace before pixel data
+
+    let [_, width, height, max] = fields;
+    if (&ppm[..2] != b"P6") || (max > 255) { return None }
+
+    let pixels = ppm.get_mut(pos..pos + (width * height * 3))?;
+    for (i, pixel) in pixels.chunks_exact_mut(3).enumerate(){
+        let (x,y) = (i % width, i / width);
+        let edge = (x < EDGE) || (y < EDGE) || (x >= width - EDGE) || (y >= height - EDGE);
+        if !edge {
+            for c in pixel.iter_mut() {
+                *c /= 4;
             }
         }
-        self.set(board);
-        true
     }
-
-}
\ No newline at end of file
+    Some(())
+}
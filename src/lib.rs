@@ -7,122 +7,335 @@ extern crate log;
 extern crate uefi;
 
 use uefi::prelude::*;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::clock::Clock;
 use crate::graphical::*;
+use crate::menu::{Menu, MenuInput};
 use uefi_things::glib::GraphicsHandle;
+use tetros_core::{Game, Action, Leaderboard, Mode, ModeKind, Puzzle};
 
 
+pub mod clock;
 pub mod graphical;
+pub mod menu;
+pub mod screens;
+pub mod storage;
 
+/// length of one pass of the game loop in ms, game time is measured by [Clock]
+const FRAME: u64 = 16;
 
 pub fn run(st: &mut uefi::table::SystemTable<uefi::prelude::Boot>) -> uefi::Result<()>{
     // Get required protocols
-    use rand::RngCore;
-    use core::fmt::Write;
     use uefi_things::proto::get_proto;
     use uefi::proto::console::text::Output;
     use uefi::proto::console::gop::GraphicsOutput;
-    use graphical::*;
+    use screens::{AfterGame, TitleChoice};
 
 
     log::set_max_level(log::LevelFilter::Info);
     // initialize protocols
-    let _o = get_proto::<Output>(st.boot_services()).unwrap().unwrap();
+    let o = get_proto::<Output>(st.boot_services()).unwrap().unwrap();
+    let _ = o.enable_cursor(false); //not supported by all firmware
     let mut g = uefi_things::glib::GraphicsHandle::new(
         uefi_things::proto::get_proto::<GraphicsOutput>(st.boot_services()).unwrap().unwrap(),
     None,
     );
-    //create game board
-    let mut board = Board::new(st, &g);
-    g.new_buff();
-    //create game boarder
-
-    board.render_bg(g.mut_get_buff(0).unwrap());
-
-    g.draw(0).unwrap().unwrap(); //should be only call to g.draw during Gameplay
-    board.draw(&mut g).unwrap().unwrap(); //do not draw board to stored buffers it will waste time //TODO handle this
-
-    let mut tetrominos = alloc::vec::Vec::new();
-
-    {
-        let l_shape = Tetromino::new((3,3),Tetromino::L_SHAPE,BlockColour::Blue);
-        let square = Tetromino::new((2,2),Tetromino::SQUARE,BlockColour::Yellow);
-        let j_shape = Tetromino::new((3,3),Tetromino::L_SHAPE_R,BlockColour::Red);
-        let z_shape = Tetromino::new((3,3), Tetromino::Z_SHAPE,BlockColour::Green);
-        let s_shape = Tetromino::new((3,3),Tetromino::Z_SHAPE_R,BlockColour::Orange);
-        let i_shape = Tetromino::new((4,1),Tetromino::I_SHAPE,BlockColour::Cyan);
-        let t_shape = Tetromino::new((3,3),Tetromino::T_SHAPE,BlockColour::Purple);
-
-        tetrominos.push(l_shape);
-        tetrominos.push(square);
-        tetrominos.push(t_shape);
-        tetrominos.push(j_shape);
-        tetrominos.push(z_shape);
-        tetrominos.push(s_shape);
-        tetrominos.push(i_shape);
-
+    g.new_buff(); //background, see graphical::draw_background
+
+    let mut options = Options::default();
+    let mut state = State::Title;
+    loop {
+        state = match state {
+            State::Title => match screens::title(st, &g, o) {
+                TitleChoice::Play => State::ModeSelect,
+                TitleChoice::Options => State::Options,
+                TitleChoice::Quit => State::Quit,
+            },
+            State::ModeSelect => match screens::mode_select(st, &g, o) {
+                Some(ModeKind::Puzzle) => State::PuzzleSelect,
+                Some(kind) if kind.setting().is_some() => match screens::mode_setup(st, &g, o, kind) {
+                    Some(kind) => State::Playing(kind),
+                    None => State::ModeSelect,
+                },
+                Some(kind) => State::Playing(kind),
+                None => State::Title,
+            },
+            State::Options => {
+                o.clear().unwrap().unwrap();
+                screens::options(st, &g, o, (0,0), g.get_resolution(), &mut options);
+                State::Title
+            }
+            State::PuzzleSelect => {
+                let puzzles = storage::load_puzzles(st);
+                match screens::puzzle_select(st, &g, o, &puzzles) {
+                    Some(index) => State::Puzzle{ puzzles, index },
+                    None => State::ModeSelect,
+                }
+            }
+            State::Playing(kind) => {
+                let mode = kind.build();
+                match play(st, &mut g, o, &*mode, &mut options) {
+                    Ending::Over(game) => {
+                        let title = mode.title(&game);
+                        let results = mode.results(&game);
+                        let scores = storage::load_scores(st, &*mode);
+                        match mode.record(&game).filter(|value| scores.place(*value).is_some()) {
+                            Some(value) => State::HighScore{ kind, title, results, scores, value },
+                            None => State::GameOver{ kind, title, results, scores, place: None },
+                        }
+                    }
+                    Ending::MainMenu => State::Title,
+                    Ending::Quit => State::Quit,
+                }
+            }
+            State::HighScore{ kind, title, results, mut scores, value } => {
+                let name = screens::enter_name(st, &g, o, scores.place(value).unwrap_or(0));
+                let place = scores.insert(&name, value);
+                if let Err(e) = storage::save_scores(st, kind, &scores) {
+                    warn!("failed to save high scores: {:?}", e.status());
+                }
+                State::GameOver{ kind, title, results, scores, place }
+            }
+            State::GameOver{ kind, title, results, scores, place } => {
+                match screens::game_over(st, &g, o, &*kind.build(), title, &results, &scores, place, false) {
+                    AfterGame::Retry => State::Playing(kind),
+                    AfterGame::Next | AfterGame::MainMenu => State::Title,
+                }
+            }
+            State::Puzzle{ puzzles, index } => {
+                let puzzle = &puzzles[index];
+                match play(st, &mut g, o, puzzle, &mut options) {
+                    Ending::Over(game) => {
+                        let title = puzzle.title(&game);
+                        let results = puzzle.results(&game);
+                        State::PuzzleOver{ puzzles, index, title, results }
+                    }
+                    Ending::MainMenu => State::Title,
+                    Ending::Quit => State::Quit,
+                }
+            }
+            State::PuzzleOver{ puzzles, index, title, results } => {
+                let puzzle = &puzzles[index];
+                let scores = Leaderboard::new(puzzle.ranking());
+                let next = index + 1 < puzzles.len();
+                match screens::game_over(st, &g, o, puzzle, title, &results, &scores, None, next) {
+                    AfterGame::Retry => State::Puzzle{ puzzles, index },
+                    AfterGame::Next => State::Puzzle{ puzzles, index: index + 1 },
+                    AfterGame::MainMenu => State::Title,
+                }
+            }
+            State::Quit => break,
+        }
     }
+    o.clear().unwrap().unwrap();
+    let _ = o.enable_cursor(true);
+    Ok(uefi::Status::SUCCESS.into())
+}
 
-    /*
-    let mut l_shape = Tetromino::new((3,3),Tetromino::L_SHAPE,BlockColour::Blue);
-    let mut square = Tetromino::new((2,2),Tetromino::SQUARE,BlockColour::Yellow);
-
-    l_shape.location = (3,3);
-    square.location = (5,3);
-    //square.set(&mut board);
-    */
-
-    let mut rng = {
-        use rand::SeedableRng;
-        use core::arch::asm;
-
-        let seed: u64;
+/// screens the program moves between, pausing is handled inside [play]
+enum State{
+    Title,
+    ModeSelect,
+    Options,
+    Playing(ModeKind),
+    /// a finished game made the high score table and needs a name
+    HighScore{ kind: ModeKind, title: &'static str, results: Vec<String>, scores: Leaderboard, value: u64 },
+    /// results of the last game, `place` is where it was put in `scores`
+    GameOver{ kind: ModeKind, title: &'static str, results: Vec<String>, scores: Leaderboard, place: Option<usize> },
+    /// puzzles read from the boot partition to pick from
+    PuzzleSelect,
+    /// playing `puzzles[index]`, the rest are kept for moving on to the next one
+    Puzzle{ puzzles: Vec<Puzzle>, index: usize },
+    PuzzleOver{ puzzles: Vec<Puzzle>, index: usize, title: &'static str, results: Vec<String> },
+    Quit,
+}
 
-        unsafe {
+/// how [play] finished
+enum Ending{
+    /// the player topped out or the mode's goal was reached
+    Over(Game),
+    MainMenu,
+    Quit,
+}
 
-            asm!("rdrand {}", out(reg) seed);
-        }
-        info!("Seed {}", seed);
+/// plays one game of `mode`, restarting from the pause menu plays another
+/// modes with a board too large for the screen are refused
+fn play(st: &SystemTable<Boot>, g: &mut GraphicsHandle, o: &mut uefi::proto::console::text::Output, mode: &dyn Mode, options: &mut Options) -> Ending{
+    //the check in main only covers the default board
+    let needed = screen_size(&mode.config());
+    let (width,height) = g.get_resolution();
+    if (width < needed.0) || (height < needed.1) {
+        warn!("{:?} needs at least {}x{}, screen is {}x{}", mode.kind(), needed.0, needed.1, width, height);
+        screens::too_small(st, g, o, needed);
+        return Ending::MainMenu
+    }
 
-        rand::rngs::SmallRng::seed_from_u64(seed)
+    let mut game = mode.new_game(seed());
 
-    };
+    o.clear().unwrap().unwrap();
+    let mut view = BoardView::new(st, g, &game);
+    view.set_ghost(options.ghost);
+    let mut hud = Hud::new(o, g, view.hud_location());
+    hud.set_best(storage::load_scores(st, mode).best().map(|best| mode.format_record(best)));
+    draw_background(g, &view).unwrap().unwrap();
+    view.draw(g).unwrap().unwrap(); //do not draw board to stored buffers it will waste time //TODO handle this
+    let mut clock = Clock::new(st);
 
     //main game loop
     'main: loop {
-        let tet: usize = {
-            let mut rand: [u8; 1] = [0];
-            rng.fill_bytes(&mut rand);
-            rand[0] as usize % tetrominos.len()
-        };
-
-        tetrominos[tet].location = (3, 0);
-        tetrominos[tet].set(&mut board);
+        game.spawn_next();
+        //block out, the new piece overlaps the stack
+        if game.is_lost() || mode.finished(&game) {
+            break
+        }
 
 
         'fall: loop {
 
-            board.draw(&mut g).unwrap().unwrap();
-            let game_action = |key| -> bool { do_game_action(&mut tetrominos[tet], &mut board, key, &mut g) }; //TODO get random tetromino
-            if tick(st, 1_000, game_action) { break 'main }
+            view.update(&game);
+            view.draw(g).unwrap().unwrap();
+            hud.draw(o, &game, mode).unwrap().unwrap();
+            let mut flow = Flow::Continue;
+            let game_action = |key| -> bool {
+                flow = do_game_action(&mut game, &mut clock, &mut view, &mut hud, mode, key, g, o);
+                flow != Flow::Continue
+            };
+            tick(st, FRAME, game_action);
+            match flow {
+                Flow::Pause => match pause(st, g, o, &mut view, &game, options) {
+                    Paused::Resume => {
+                        clock.reset();
+                        continue 'fall
+                    }
+                    Paused::Restart => {
+                        game = mode.new_game(seed());
+                        hud.reset();
+                        clock.reset();
+                        continue 'main
+                    }
+                    Paused::MainMenu => return Ending::MainMenu,
+                    Paused::Quit => return Ending::Quit,
+                },
+                Flow::Locked => break 'fall,
+                Flow::Continue => {}
+            }
 
-            //drop one block, on fail break
-            if let false = tetrominos[tet].legal_move((0,1),&mut board){
-                //TODO if at top break 'main
+            //apply gravity, on fail the piece is locked and rows are cleared
+            if let false = game.advance(clock.lap()){
                 break 'fall
             }
+            if mode.finished(&game) {
+                break 'main
+            }
         }
-        board.clean_screen();
-        board.draw(&mut g).unwrap().unwrap();
-        if board.is_lost(){
+        view.update(&game);
+        view.draw(g).unwrap().unwrap();
+        hud.events(game.take_events());
+        hud.draw(o, &game, mode).unwrap().unwrap();
+        //lock out, the piece locked above the visible rows
+        if game.is_lost() || mode.finished(&game) {
             break
         }
     }
-    uefi_things::proto::get_proto::<Output>(st.boot_services()).unwrap().unwrap().clear().unwrap().unwrap();
-    writeln!(st.stdout(),"You loose score {}",0).unwrap();
-    Ok(uefi::Status::SUCCESS.into())
+    Ending::Over(game)
 }
 
 
+/// new random seed for a game
+fn seed() -> u64 {
+    use core::arch::asm;
+    let seed: u64;
+
+    unsafe {
+
+        asm!("rdrand {}", out(reg) seed);
+    }
+    info!("Seed {}", seed);
+    seed
+}
+
+/// blocks until a key is pressed
+fn wait_key(st: &SystemTable<Boot>) -> uefi::proto::console::text::Key {
+    let kb = uefi_things::proto::get_proto::<uefi::proto::console::text::Input>(st.boot_services()).unwrap().unwrap();
+    loop {
+        let key_event = unsafe{ kb.wait_for_key_event().unsafe_clone() };
+        st.boot_services().wait_for_event(&mut [key_event]).expect_success("Failed to wait for key event");
+        if let Some(k) = kb.read_key().expect_success("Failed to get key."){
+            return k
+        }
+    }
+}
+
+/// settings changed from the options menu
+#[derive(Clone,Copy)]
+pub struct Options{
+    pub ghost: bool,
+}
+
+impl Default for Options{
+    fn default() -> Self{
+        Self{
+            ghost: true,
+        }
+    }
+}
+
+/// what the player picked from the pause menu
+enum Paused{
+    Resume,
+    Restart,
+    MainMenu,
+    Quit,
+}
+
+/// where the board is saved by the pause menu, it is also written to the log
+const FUMEN_PATH: &str = "/tetros/fumen.txt";
+
+/// covers the board and shows the pause menu until something is picked
+/// gravity doesn't run as [Game::advance] isn't called
+fn pause(st: &SystemTable<Boot>, g: &mut GraphicsHandle, o: &mut uefi::proto::console::text::Output, view: &mut BoardView, game: &Game, options: &mut Options) -> Paused {
+    view.cover(g).unwrap().unwrap();
+    let (location,size) = view.area();
+    let mut menu = Menu::new(o, g, location, size, "Paused", &["Resume", "Restart", "Options", "Export fumen", "Main menu", "Quit"]);
+
+    let paused = loop {
+        menu.draw(o).unwrap().unwrap();
+        match menu.key(wait_key(st)) {
+            MenuInput::Chosen(0) | MenuInput::Back => break Paused::Resume,
+            MenuInput::Chosen(1) => break Paused::Restart,
+            MenuInput::Chosen(2) => {
+                menu.clear(o).unwrap().unwrap();
+                screens::options(st, g, o, location, size, options);
+                view.set_ghost(options.ghost);
+            }
+            MenuInput::Chosen(3) => {
+                let message = match tetros_core::fumen::encode(game.board(), game.active()) {
+                    Ok(code) => {
+                        info!("fumen {}", code);
+                        match storage::write_text(st, FUMEN_PATH, &code) {
+                            Ok(_) => alloc::format!("Saved to {}", FUMEN_PATH),
+                            Err(e) => {
+                                warn!("failed to save fumen: {:?}", e.status());
+                                String::from("Written to the log")
+                            }
+                        }
+                    }
+                    Err(e) => alloc::format!("{}", e),
+                };
+                menu.clear(o).unwrap().unwrap();
+                menu.set_text(alloc::vec![message]);
+            }
+            MenuInput::Chosen(4) => break Paused::MainMenu,
+            MenuInput::Chosen(_) => break Paused::Quit,
+            MenuInput::Moved | MenuInput::Change(..) | MenuInput::None => {}
+        }
+    };
+    menu.clear(o).unwrap().unwrap();
+    //board is drawn again by the game loop
+    paused
+}
+
 pub fn tick<T>(st: &SystemTable<Boot>,time: u64 , mut action: T) -> bool
     where T: FnMut(uefi::proto::console::text::Key) -> bool
 {
@@ -160,9 +373,18 @@ pub fn tick<T>(st: &SystemTable<Boot>,time: u64 , mut action: T) -> bool
 
 
 
-fn do_game_action(tet: &mut Tetromino,board: &mut Board ,key: uefi::proto::console::text::Key ,g: &mut GraphicsHandle) -> bool{
+/// what the game loop should do after a key press
+#[derive(PartialEq)]
+enum Flow{
+    Continue,
+    /// the piece was locked by the key, skip the rest of the tick
+    Locked,
+    Pause,
+}
 
-    match key {
+fn do_game_action(game: &mut Game, clock: &mut Clock, view: &mut BoardView, hud: &mut Hud, mode: &dyn Mode, key: uefi::proto::console::text::Key ,g: &mut GraphicsHandle, o: &mut uefi::proto::console::text::Output) -> Flow{
+
+    let action = match key {
         uefi::proto::console::text::Key::Printable(key) => {
             let key = key.into();
             match key{
@@ -170,42 +392,61 @@ fn do_game_action(tet: &mut Tetromino,board: &mut Board ,key: uefi::proto::conso
 
                 'e' => {
                     trace!("got e");
-                    tet.safe_ror(board);
-                    board.draw(g).unwrap().unwrap();
+                    Action::RotateRight
                 }
                 'q' => {
                     trace!("got q");
-                    tet.safe_rol(board);
-                    board.draw(g).unwrap().unwrap();
+                    Action::RotateLeft
                 }
 
                 // left right movement
                 'a' => {
                     trace!("Go right");
-                    tet.legal_move((-1,0),board);
-                    board.draw(g).unwrap().unwrap();
+                    Action::MoveLeft
                 }
                 'd' => {
                     trace!("Go left");
-                    tet.legal_move((1,0), board);
-                    board.draw(g).unwrap().unwrap();
+                    Action::MoveRight
                 }
 
                 //fast drop
                 's' => {
                     trace!("dropping");
-                    tet.legal_move((0,1),board);
-                    board.draw(g).unwrap().unwrap();
+                    Action::SoftDrop
+                }
+                //hard drop
+                'w' => {
+                    trace!("hard drop");
+                    Action::HardDrop
+                }
+
+                // swap with hold slot
+                'c' => {
+                    trace!("hold");
+                    Action::Hold
                 }
-                'w' => {}
 
-                e => {trace!("got something {}",e);} //do nothing
+                e => {trace!("got something {}",e); return Flow::Continue} //do nothing
             }
         }
         uefi::proto::console::text::Key::Special(uefi::proto::console::text::ScanCode::ESCAPE) => {
-            return true
-        } //pause
-        _ => {}
+            return Flow::Pause
+        }
+        _ => { return Flow::Continue }
+    };
+
+    //bring the game up to the time of the key press, gravity or lock delay may have run out
+    if !game.advance(clock.lap()) {
+        return Flow::Locked
     }
-    return false
-}
\ No newline at end of file
+    if game.action(action) {
+        view.update(game);
+        view.draw(g).unwrap().unwrap();
+        hud.draw(o, game, mode).unwrap().unwrap();
+    }
+
+    if game.active().is_none() {
+        return Flow::Locked
+    }
+    return Flow::Continue
+}
@@ -22,9 +22,10 @@ fn main(_image: Handle, mut st: SystemTable<Boot>) -> Status {
     {
         let gop = uefi_things::proto::get_proto::<uefi::proto::console::gop::GraphicsOutput>(st.boot_services()).unwrap().unwrap();
         let (width,height) = gop.current_mode_info().resolution();
+        let (min_width,min_height) = graphical::screen_size(&tetros_core::Config::default());
 
-        if (width < graphical::BOARD_WIDTH) || (height < graphical::BOARD_HEIGHT){
-            writeln!(o,"unsupported resolution requites at least {}x{}", graphical:: BOARD_WIDTH,graphical::BOARD_HEIGHT).unwrap();
+        if (width < min_width) || (height < min_height){
+            writeln!(o,"unsupported resolution requites at least {}x{}", min_width,min_height).unwrap();
             return Status::UNSUPPORTED
         }
 
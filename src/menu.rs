@@ -0,0 +1,131 @@
+//! text menus drawn with the text console on top of the graphics
+
+use alloc::fmt::Write;
+use alloc::string::String;
+use alloc::vec::Vec;
+use uefi::Status;
+use uefi::proto::console::text::{Key, Output, ScanCode};
+use uefi_things::glib::GraphicsHandle;
+use crate::graphical::{GLYPH, text_cell};
+
+/// what a key press did to a [Menu]
+#[derive(PartialEq)]
+pub enum MenuInput{
+    /// the key meant nothing to the menu
+    None,
+    /// the selection moved, the menu should be drawn again
+    Moved,
+    /// item at this index was picked with enter or space
+    Chosen(usize),
+    /// left or right was pressed on the item at this index, -1 for left
+    Change(usize,i32),
+    /// escape was pressed
+    Back,
+}
+
+/// a title and a list of items, one of which is selected
+/// up/down or w/s move the selection, left/right or a/d change the selected item
+pub struct Menu{
+    position: (usize,usize), //column and row of the top left of the area
+    columns: usize,
+    rows: usize,
+
+    title: String,
+    text: Vec<String>, //shown between the title and the items
+    items: Vec<String>,
+    selected: usize,
+}
+
+impl Menu{
+    /// the menu is centred in the `size` pixels at `location`
+    pub fn new(o: &mut Output, g: &GraphicsHandle, location: (usize,usize), size: (usize,usize), title: &str, items: &[&str]) -> Self{
+        Self{
+            position: text_cell(o,g,location),
+            columns: size.0 / GLYPH.0,
+            rows: size.1 / GLYPH.1,
+            title: String::from(title),
+            text: Vec::new(),
+            items: items.iter().map(|i| String::from(*i)).collect(),
+            selected: 0,
+        }
+    }
+
+    /// sets lines shown under the title, for results and prompts
+    pub fn set_text(&mut self, text: Vec<String>){
+        self.text = text;
+    }
+
+    /// changes the text of an item, used for items showing a setting
+    pub fn set_item(&mut self, index: usize, text: String){
+        if let Some(item) = self.items.get_mut(index) {
+            *item = text;
+        }
+    }
+
+    pub fn key(&mut self, key: Key) -> MenuInput{
+        let up = match key {
+            Key::Special(ScanCode::UP) => true,
+            Key::Special(ScanCode::DOWN) => false,
+            Key::Special(ScanCode::ESCAPE) => return MenuInput::Back,
+            Key::Special(ScanCode::LEFT) => return MenuInput::Change(self.selected,-1),
+            Key::Special(ScanCode::RIGHT) => return MenuInput::Change(self.selected,1),
+            Key::Printable(c) => match char::from(c) {
+                'w' => true,
+                's' => false,
+                'a' => return MenuInput::Change(self.selected,-1),
+                'd' => return MenuInput::Change(self.selected,1),
+                '\r' | ' ' => return MenuInput::Chosen(self.selected),
+                _ => return MenuInput::None,
+            },
+            _ => return MenuInput::None,
+        };
+
+        let len = self.items.len();
+        if len == 0 { return MenuInput::None }
+        self.selected = if up { (self.selected + len - 1) % len } else { (self.selected + 1) % len };
+        MenuInput::Moved
+    }
+
+    /// lines of the menu, the selected item is marked
+    fn lines(&self) -> Vec<String>{
+        let mut lines = Vec::new();
+        lines.push(self.title.clone());
+        lines.push(String::new());
+        if !self.text.is_empty() {
+            lines.extend(self.text.iter().cloned());
+            lines.push(String::new());
+        }
+        for (i,item) in self.items.iter().enumerate(){
+            if i == self.selected {
+                lines.push(alloc::format!("> {} <",item));
+            } else {
+                lines.push(item.clone());
+            }
+        }
+        lines
+    }
+
+    /// first row used, centres the menu vertically
+    fn top(&self) -> usize{
+        self.position.1 + (self.rows.saturating_sub(self.lines().len()) / 2)
+    }
+
+    pub fn draw(&self, o: &mut Output) -> uefi::Result{
+        let top = self.top();
+        for (i,line) in self.lines().iter().enumerate(){
+            o.set_cursor_position(self.position.0,top + i)?.unwrap();
+            write!(o,"{:^width$.width$}",line,width = self.columns).unwrap();
+        }
+        Ok(Status::SUCCESS.into())
+    }
+
+    /// blanks every line the menu uses
+    pub fn clear(&self, o: &mut Output) -> uefi::Result{
+        let top = self.top();
+        for i in 0..self.lines().len(){
+            o.set_cursor_position(self.position.0,top + i)?.unwrap();
+            write!(o,"{:width$}","",width = self.columns).unwrap();
+        }
+        Ok(Status::SUCCESS.into())
+    }
+}
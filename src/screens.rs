@@ -0,0 +1,186 @@
+//! full screen menus shown between games
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use uefi::prelude::*;
+use uefi::proto::console::text::{Key, Output, ScanCode};
+use uefi_things::glib::GraphicsHandle;
+use tetros_core::{Leaderboard, Mode, ModeKind, Puzzle};
+use crate::menu::{Menu, MenuInput};
+use crate::{Options, wait_key};
+
+/// what was picked on the title screen
+pub enum TitleChoice{
+    Play,
+    Options,
+    Quit,
+}
+
+/// what was picked after a game
+pub enum AfterGame{
+    Retry,
+    /// only offered for puzzles
+    Next,
+    MainMenu,
+}
+
+/// a menu filling the whole screen
+fn screen_menu(o: &mut Output, g: &GraphicsHandle, title: &str, items: &[&str]) -> Menu{
+    o.clear().unwrap().unwrap();
+    Menu::new(o, g, (0,0), g.get_resolution(), title, items)
+}
+
+pub fn title(st: &SystemTable<Boot>, g: &GraphicsHandle, o: &mut Output) -> TitleChoice{
+    let mut menu = screen_menu(o, g, "T E T R O S", &["Play", "Options", "Quit"]);
+    loop {
+        menu.draw(o).unwrap().unwrap();
+        match menu.key(wait_key(st)) {
+            MenuInput::Chosen(0) => return TitleChoice::Play,
+            MenuInput::Chosen(1) => return TitleChoice::Options,
+            MenuInput::Chosen(_) => return TitleChoice::Quit,
+            MenuInput::Moved | MenuInput::Change(..) | MenuInput::None | MenuInput::Back => {}
+        }
+    }
+}
+
+/// returns `None` if the player went back, puzzles are picked with [puzzle_select]
+pub fn mode_select(st: &SystemTable<Boot>, g: &GraphicsHandle, o: &mut Output) -> Option<ModeKind>{
+    let mut items: Vec<&str> = ModeKind::ALL.iter().map(|m| m.name()).collect();
+    items.push("Puzzles");
+    items.push("Back");
+    let mut menu = screen_menu(o, g, "Mode", &items);
+    loop {
+        menu.draw(o).unwrap().unwrap();
+        match menu.key(wait_key(st)) {
+            MenuInput::Chosen(i) if i == ModeKind::ALL.len() => return Some(ModeKind::Puzzle),
+            MenuInput::Chosen(i) => return ModeKind::ALL.get(i).copied(),
+            MenuInput::Back => return None,
+            MenuInput::Moved | MenuInput::Change(..) | MenuInput::None => {}
+        }
+    }
+}
+
+/// picks the value of `kind`'s setting, returns `None` if the player went back
+pub fn mode_setup(st: &SystemTable<Boot>, g: &GraphicsHandle, o: &mut Output, kind: ModeKind) -> Option<ModeKind>{
+    let mut kind = kind;
+    let mut menu = screen_menu(o, g, kind.name(), &["", "Start", "Back"]);
+    loop {
+        if let Some(setting) = kind.setting() {
+            menu.set_item(0, alloc::format!("{} < {} >", setting.name, setting.value));
+        }
+        menu.draw(o).unwrap().unwrap();
+        match menu.key(wait_key(st)) {
+            MenuInput::Change(0,step) => if let Some(setting) = kind.setting() {
+                let value = setting.value as i64 + (step as i64 * setting.step as i64);
+                kind = kind.with_setting(value.max(0) as u32);
+            },
+            MenuInput::Chosen(0) | MenuInput::Chosen(1) => return Some(kind),
+            MenuInput::Chosen(_) | MenuInput::Back => return None,
+            MenuInput::Moved | MenuInput::Change(..) | MenuInput::None => {}
+        }
+    }
+}
+
+/// returns the index of the picked puzzle, `None` if the player went back
+pub fn puzzle_select(st: &SystemTable<Boot>, g: &GraphicsHandle, o: &mut Output, puzzles: &[Puzzle]) -> Option<usize>{
+    let mut items: Vec<&str> = puzzles.iter().map(|p| p.name.as_str()).collect();
+    items.push("Back");
+    let mut menu = screen_menu(o, g, "Puzzles", &items);
+    if puzzles.is_empty() {
+        menu.set_text(alloc::vec![String::from("No puzzles found in /tetros/puzzles/")]);
+    }
+    loop {
+        menu.draw(o).unwrap().unwrap();
+        match menu.key(wait_key(st)) {
+            MenuInput::Chosen(i) => return (i < puzzles.len()).then_some(i),
+            MenuInput::Back => return None,
+            MenuInput::Moved | MenuInput::Change(..) | MenuInput::None => {}
+        }
+    }
+}
+
+/// changes `options` until back is picked
+/// the menu is centred in the `size` pixels at `location` so it can be shown over the board
+pub fn options(st: &SystemTable<Boot>, g: &GraphicsHandle, o: &mut Output, location: (usize,usize), size: (usize,usize), options: &mut Options){
+    let mut menu = Menu::new(o, g, location, size, "Options", &["", "Back"]);
+    loop {
+        let ghost = if options.ghost { "on" } else { "off" };
+        menu.set_item(0, alloc::format!("Ghost piece {}", ghost));
+        menu.draw(o).unwrap().unwrap();
+        match menu.key(wait_key(st)) {
+            MenuInput::Chosen(0) | MenuInput::Change(0,_) => options.ghost = !options.ghost,
+            MenuInput::Chosen(_) | MenuInput::Back => break,
+            MenuInput::Moved | MenuInput::Change(..) | MenuInput::None => {}
+        }
+    }
+    menu.clear(o).unwrap().unwrap();
+}
+
+/// shown instead of a game whose board doesn't fit on the screen,
+/// `needed` is the resolution it asks for
+pub fn too_small(st: &SystemTable<Boot>, g: &GraphicsHandle, o: &mut Output, needed: (usize,usize)){
+    let (width,height) = g.get_resolution();
+    let mut menu = screen_menu(o, g, "Board too large", &["Back"]);
+    menu.set_text(alloc::vec![
+        alloc::format!("This mode needs at least {}x{}", needed.0, needed.1),
+        alloc::format!("The screen is {}x{}", width, height),
+    ]);
+    loop {
+        menu.draw(o).unwrap().unwrap();
+        match menu.key(wait_key(st)) {
+            MenuInput::Chosen(_) | MenuInput::Back => break,
+            MenuInput::Moved | MenuInput::Change(..) | MenuInput::None => {}
+        }
+    }
+}
+
+/// asks for a name for the high score table
+pub fn enter_name(st: &SystemTable<Boot>, g: &GraphicsHandle, o: &mut Output, place: usize) -> String{
+    let mut menu = screen_menu(o, g, "New high score", &[]);
+    let mut name = String::new();
+    loop {
+        menu.set_text(alloc::vec![
+            alloc::format!("Place {}", place + 1),
+            alloc::format!("Name {:_<width$}", name, width = Leaderboard::NAME_LEN),
+        ]);
+        menu.draw(o).unwrap().unwrap();
+        match wait_key(st) {
+            Key::Printable(c) => match char::from(c) {
+                '\r' => break,
+                '\u{8}' => { name.pop(); }
+                c if c.is_ascii_alphanumeric() && (name.len() < Leaderboard::NAME_LEN) => name.push(c),
+                _ => {}
+            },
+            Key::Special(ScanCode::ESCAPE) => break,
+            _ => {}
+        }
+    }
+    name
+}
+
+/// results of a game and the high scores of `mode`, `place` marks a new entry
+/// `next` offers moving on to the next puzzle
+pub fn game_over(st: &SystemTable<Boot>, g: &GraphicsHandle, o: &mut Output, mode: &dyn Mode, title: &str, results: &[String], scores: &Leaderboard, place: Option<usize>, next: bool) -> AfterGame{
+    let mut text: Vec<String> = results.to_vec();
+    if !scores.entries().is_empty() {
+        text.push(String::new());
+        text.push(String::from("High scores"));
+    }
+    for (i,entry) in scores.entries().iter().enumerate(){
+        let mark = if Some(i) == place { "*" } else { " " };
+        text.push(alloc::format!("{}{:>2}. {:<width$} {:>10}", mark, i + 1, entry.name, mode.format_record(entry.value), width = Leaderboard::NAME_LEN));
+    }
+
+    let items: &[&str] = if next { &["Retry", "Next puzzle", "Main menu"] } else { &["Retry", "Main menu"] };
+    let mut menu = screen_menu(o, g, title, items);
+    menu.set_text(text);
+    loop {
+        menu.draw(o).unwrap().unwrap();
+        match menu.key(wait_key(st)) {
+            MenuInput::Chosen(0) => return AfterGame::Retry,
+            MenuInput::Chosen(1) if next => return AfterGame::Next,
+            MenuInput::Chosen(_) | MenuInput::Back => return AfterGame::MainMenu,
+            MenuInput::Moved | MenuInput::Change(..) | MenuInput::None => {}
+        }
+    }
+}
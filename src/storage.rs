@@ -0,0 +1,135 @@
+//! small text files kept on the boot partition under `/tetros/`
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use uefi::Status;
+use uefi::prelude::*;
+use uefi::proto::media::file::{File, FileAttribute, FileMode, FileType};
+use uefi::proto::media::fs::SimpleFileSystem;
+use uefi_things::fs::GetFileStatus;
+use tetros_core::{Leaderboard, Mode, ModeKind, Puzzle};
+
+/// returns the contents of the file at `path`, `None` if it is missing or isn't text
+pub fn read_text(st: &SystemTable<Boot>, path: &str) -> Option<String>{
+    let fs = uefi_things::proto::get_proto::<SimpleFileSystem>(st.boot_services()).unwrap().unwrap();
+    let file = match uefi_things::fs::get_file_from_path(fs,path,FileMode::Read,FileAttribute::empty()){
+        GetFileStatus::Found(f) => f.into_type().ok()?.unwrap(),
+        GetFileStatus::NotFound(_) => return None,
+        GetFileStatus::Err(e) => {
+            warn!("failed to open {}: {:?}",path,e);
+            return None
+        }
+    };
+
+    match file{
+        FileType::Regular(f) => String::from_utf8(uefi_things::fs::read_file(f).ok()?.unwrap()).ok(),
+        FileType::Dir(_) => None,
+    }
+}
+
+/// names of the regular files in the directory at `path`, sorted, empty if it is missing
+pub fn list_dir(st: &SystemTable<Boot>, path: &str) -> Vec<String>{
+    use uefi::proto::media::file::FileInfo;
+
+    let fs = uefi_things::proto::get_proto::<SimpleFileSystem>(st.boot_services()).unwrap().unwrap();
+    let mut dir = match uefi_things::fs::get_file_from_path(fs,path,FileMode::Read,FileAttribute::empty()){
+        GetFileStatus::Found(f) => match f.into_type() {
+            Ok(c) => match c.unwrap() {
+                FileType::Dir(d) => d,
+                FileType::Regular(_) => return Vec::new(),
+            },
+            Err(_) => return Vec::new(),
+        },
+        GetFileStatus::NotFound(_) => return Vec::new(),
+        GetFileStatus::Err(e) => {
+            warn!("failed to open {}: {:?}",path,e);
+            return Vec::new()
+        }
+    };
+
+    let mut names = Vec::new();
+    let mut buffer = alloc::vec![0u8; 512];
+    loop {
+        let info: &mut FileInfo = match dir.read_entry(&mut buffer) {
+            Ok(c) => match c.unwrap() {
+                Some(info) => info,
+                None => break, //end of the directory
+            },
+            //the buffer is too small for a long name, grow it and try again
+            Err(e) => match *e.data() {
+                Some(size) => {
+                    buffer.resize(size,0);
+                    continue
+                }
+                None => break,
+            },
+        };
+        if info.attribute().contains(FileAttribute::DIRECTORY) { continue }
+        names.push(char::decode_utf16(info.file_name().to_u16_slice().iter().copied())
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect());
+    }
+    names.sort();
+    names
+}
+
+/// replaces the file at `path` with `text`
+pub fn write_text(st: &SystemTable<Boot>, path: &str, text: &str) -> uefi::Result{
+    let fs = uefi_things::proto::get_proto::<SimpleFileSystem>(st.boot_services()).unwrap().unwrap();
+
+    //delete any old copy so nothing is left past the end of the new text
+    if let GetFileStatus::Found(f) = uefi_things::fs::get_file_from_path(fs,path,FileMode::ReadWrite,FileAttribute::empty()){
+        f.delete()?.unwrap();
+    }
+
+    let file = match uefi_things::fs::get_file_from_path(fs,path,FileMode::CreateReadWrite,FileAttribute::empty()){
+        GetFileStatus::Found(f) => f.into_type()?.unwrap(),
+        GetFileStatus::NotFound(_) => return Err(Status::NOT_FOUND.into()),
+        GetFileStatus::Err(e) => return Err(e.into()),
+    };
+
+    match file{
+        FileType::Regular(mut f) => {
+            f.write(text.as_bytes()).map_err(|e| e.status())?.unwrap();
+            f.flush()?.unwrap();
+            Ok(Status::SUCCESS.into())
+        }
+        FileType::Dir(_) => Err(Status::ACCESS_DENIED.into()),
+    }
+}
+
+fn scores_path(kind: ModeKind) -> String{
+    alloc::format!("/tetros/scores_{}.txt",kind.id())
+}
+
+/// high scores of `mode`, empty if none have been saved
+pub fn load_scores(st: &SystemTable<Boot>, mode: &dyn Mode) -> Leaderboard{
+    match read_text(st,&scores_path(mode.kind())){
+        Some(text) => Leaderboard::parse(mode.ranking(),&text),
+        None => Leaderboard::new(mode.ranking()),
+    }
+}
+
+const PUZZLE_PATH: &str = "/tetros/puzzles/";
+
+/// every `.txt` puzzle in [PUZZLE_PATH] in name order, ones that don't parse are skipped with a warning
+pub fn load_puzzles(st: &SystemTable<Boot>) -> Vec<Puzzle>{
+    let mut puzzles = Vec::new();
+    for file in list_dir(st,PUZZLE_PATH) {
+        let name = match file.strip_suffix(".txt").or_else(|| file.strip_suffix(".TXT")) {
+            Some(name) => name,
+            None => continue,
+        };
+        let path = alloc::format!("{}{}",PUZZLE_PATH,file);
+        match read_text(st,&path).map(|text| Puzzle::parse(name,&text)) {
+            Some(Ok(puzzle)) => puzzles.push(puzzle),
+            Some(Err(e)) => warn!("skipping puzzle {}: {}",path,e),
+            None => warn!("failed to read puzzle {}",path),
+        }
+    }
+    puzzles
+}
+
+pub fn save_scores(st: &SystemTable<Boot>, kind: ModeKind, scores: &Leaderboard) -> uefi::Result{
+    write_text(st,&scores_path(kind),&alloc::format!("{}",scores))
+}
@@ -0,0 +1,314 @@
+use alloc::vec::Vec;
+
+/// game grid, only contains locked blocks
+/// the falling tetromino is kept by [crate::Game]
+/// the top `hidden` rows are a buffer zone above the visible field
+///
+/// each row is a bitmask with bit `x` set when column `x` is filled,
+/// colours are only looked at for drawing
+#[derive(Clone)]
+pub struct Board{
+    //size in blocks, `height` includes the hidden rows
+    width: usize,
+    height: usize,
+    hidden: usize,
+    full: u16, //mask of a full row
+
+    rows: Vec<u16>,
+    colours: Vec<BlockColour>, //contains block colours within game grid
+}
+
+impl Board{
+
+    pub const GAME_HEIGHT: usize = 18;
+    pub const GAME_WIDTH: usize = 10;
+    /// rows above [Self::GAME_HEIGHT] pieces spawn into
+    pub const HIDDEN_ROWS: usize = 2;
+
+    /// widest board a row mask can hold
+    pub const MAX_WIDTH: usize = 16;
+
+    /// `height` is the visible height, `hidden` rows are added above it
+    /// panics if `width` is over [Self::MAX_WIDTH]
+    pub fn new(width: usize, height: usize, hidden: usize) -> Self{
+        assert!(width <= Self::MAX_WIDTH,"board is too wide");
+        let height = height + hidden;
+        let colours = alloc::vec![BlockColour::None; width*height];
+        let rows = alloc::vec![0; height];
+
+        Self{
+            width,
+            height,
+            hidden,
+            full: ((1u32 << width) - 1) as u16,
+            rows,
+            colours,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// rows including the hidden ones
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// number of rows in the buffer zone, visible rows start at this row
+    pub fn hidden(&self) -> usize {
+        self.hidden
+    }
+
+    pub fn visible_height(&self) -> usize {
+        self.height - self.hidden
+    }
+
+    /// returns the colour at `at`, out of bounds is always [BlockColour::None]
+    pub fn get(&self, at: (usize,usize)) -> BlockColour {
+        let (x,y) = at;
+        if (x >= self.width) || (y >= self.height) { return BlockColour::None }
+
+        self.colours[self.index(at)]
+    }
+
+    pub fn set(&mut self, at:(usize, usize), colour: BlockColour){
+        //if out of bounds
+        if (at.0 >= self.width) || (at.1 >= self.height){ return }
+
+        let address = self.index(at);
+        self.colours[address] = colour;
+        match colour {
+            BlockColour::None => self.rows[at.1] &= !(1 << at.0),
+            _ => self.rows[at.1] |= 1 << at.0,
+        }
+    }
+
+    /// filled columns of `row` as a mask, bit 0 is the left column
+    pub fn row(&self, row: usize) -> u16 {
+        self.rows.get(row).copied().unwrap_or(0)
+    }
+
+    /// returns true if a block may be placed at `coords`
+    /// anything outside of the board is never free
+    pub fn is_free(&self,coords: (isize,isize)) -> bool{
+        let (x,y) = coords;
+
+        if x.is_negative() || y.is_negative() { return false }
+        if (x as usize >= self.width) || (y as usize >= self.height) { return false }
+
+        self.rows[y as usize] & (1 << x) == 0
+    }
+
+    /// returns true if a 4x4 piece `mask` with its top left at `at` overlaps a block or the edges
+    /// see [crate::PieceDef::rotations] for the layout of `mask`
+    pub fn collides(&self, mask: u16, at: (isize,isize)) -> bool{
+        let (x,y) = at;
+        for r in 0..4 {
+            let bits = ((mask >> (r * 4)) & 0xf) as u32;
+            if bits == 0 { continue }
+
+            let row = y + r as isize;
+            if row.is_negative() || (row as usize >= self.height) { return true }
+
+            let shifted = if x.is_negative() {
+                //anything shifted off the left is outside
+                if (x <= -4) || (bits & ((1 << -x) - 1) != 0) { return true }
+                bits >> -x
+            } else {
+                if x as usize >= self.width { return true }
+                bits << x
+            };
+            if shifted & !(self.full as u32) != 0 { return true }
+            if shifted & self.rows[row as usize] as u32 != 0 { return true }
+        }
+        false
+    }
+
+    fn index(&self,coords:(usize,usize)) -> usize {
+        (coords.1 * self.width) + coords.0
+    }
+
+    /// checks rows top..bottom returns row of first full row
+    fn find_row(&self) -> Option<usize>{
+        self.rows.iter().position(|row| *row == self.full)
+    }
+
+    /// clears given row
+    fn clear_row(&mut self, row: usize){
+        self.rows[row] = 0;
+        let start = self.index((0,row));
+        self.colours[start..start + self.width].fill(BlockColour::None);
+    }
+
+    ///scrolls all blocks above `row` down
+    fn scroll_from(&mut self, row: usize){
+        self.rows.copy_within(0..row,1);
+        let end = self.index((0,row));
+        self.colours.copy_within(0..end,self.width);
+        self.clear_row(0);
+    }
+
+    ///scrolls all blocks up a row, the reverse of `scroll_from`
+    ///returns false if the top row had blocks in it, they are lost
+    fn scroll_up(&mut self) -> bool{
+        let overflow = self.rows[0] != 0;
+        self.rows.copy_within(1..,0);
+        self.colours.copy_within(self.width..,0);
+        self.clear_row(self.height - 1);
+        !overflow
+    }
+
+    /// adds a row at the bottom shifting the stack up, columns set in `mask` are filled with `colour`
+    /// returns false if blocks were pushed off the top
+    pub fn insert_row(&mut self, mask: u16, colour: BlockColour) -> bool{
+        let fits = self.scroll_up();
+        for x in 0..self.width {
+            if mask & (1 << x) != 0 {
+                self.set((x,self.height - 1),colour);
+            }
+        }
+        fits
+    }
+
+    /// topmost row with a block in it
+    pub fn highest_block(&self) -> Option<usize>{
+        self.rows.iter().position(|row| *row != 0)
+    }
+
+    ///clears full rows and scrolls blocks down
+    pub fn clean_screen(&mut self) -> ClearResult{
+        let mut result = ClearResult::default();
+
+        while let Some(row) = self.find_row() {
+            result.lines += 1;
+            let start = self.index((0,row));
+            if self.colours[start..start + self.width].contains(&BlockColour::Grey) {
+                result.garbage += 1;
+            }
+            self.clear_row(row);
+            self.scroll_from(row);
+        }
+        result
+    }
+}
+
+/// what [Board::clean_screen] removed
+#[derive(Debug,Clone,Copy,PartialEq,Default)]
+pub struct ClearResult{
+    /// number of rows cleared
+    pub lines: usize,
+    /// how many of those rows had garbage in them, garbage is always [BlockColour::Grey]
+    pub garbage: usize,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum BlockColour{
+    Red,
+    Blue,
+    Green,
+    Cyan,
+    Grey,
+    Yellow,
+    Orange,
+    Purple,
+    None,
+}
+
+impl core::fmt::Display for BlockColour {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill_row(board: &mut Board, row: usize, colour: BlockColour) {
+        for x in 0..board.width() {
+            board.set((x,row),colour);
+        }
+    }
+
+    #[test]
+    fn out_of_bounds_is_not_free() {
+        let board = Board::new(Board::GAME_WIDTH,Board::GAME_HEIGHT,Board::HIDDEN_ROWS);
+        let height = (Board::GAME_HEIGHT + Board::HIDDEN_ROWS) as isize;
+
+        assert!(board.is_free((0,0)));
+        assert!(board.is_free((0,height - 1)));
+        assert!(!board.is_free((-1,0)));
+        assert!(!board.is_free((0,-1)));
+        assert!(!board.is_free((Board::GAME_WIDTH as isize,0)));
+        assert!(!board.is_free((0,height)));
+    }
+
+    #[test]
+    fn clean_screen_scrolls_rows_down() {
+        let mut board = Board::new(4,4,0);
+        fill_row(&mut board,3,BlockColour::Red);
+        fill_row(&mut board,2,BlockColour::Blue);
+        board.set((1,1),BlockColour::Green);
+
+        assert_eq!(board.clean_screen().lines,2);
+        assert_eq!(board.get((1,3)),BlockColour::Green);
+        for y in 0..3 {
+            for x in 0..4 {
+                assert!(board.is_free((x,y)));
+            }
+        }
+    }
+
+    #[test]
+    fn masks_collide_with_blocks_and_edges() {
+        let mut board = Board::new(4,4,0);
+        board.set((1,3),BlockColour::Grey);
+        assert_eq!(board.row(3),0b0010);
+
+        let o = 0b0011_0011;
+        assert!(!board.collides(o,(0,0)));
+        assert!(!board.collides(o,(2,2)));
+        assert!(board.collides(o,(0,2)));
+        assert!(board.collides(o,(3,0)));
+        assert!(board.collides(o,(-1,0)));
+        assert!(board.collides(o,(0,-1)));
+        assert!(board.collides(o,(0,3)));
+        //empty columns of the mask may hang off the edge
+        assert!(!board.collides(o << 1,(-1,0)));
+
+        board.set((1,3),BlockColour::None);
+        assert_eq!(board.row(3),0);
+        assert!(!board.collides(o,(0,2)));
+    }
+
+    #[test]
+    fn hidden_rows_clear_and_scroll() {
+        let mut board = Board::new(4,2,2);
+        assert_eq!(board.height(),4);
+        assert_eq!(board.visible_height(),2);
+        board.set((1,0),BlockColour::Grey);
+        fill_row(&mut board,3,BlockColour::Red);
+
+        assert_eq!(board.clean_screen().lines,1);
+        assert_eq!(board.get((1,1)),BlockColour::Grey);
+    }
+
+    #[test]
+    fn inserted_rows_push_the_stack_up() {
+        let mut board = Board::new(4,4,0);
+        board.set((0,3),BlockColour::Red);
+        assert!(board.insert_row(0b1110,BlockColour::Grey));
+        assert_eq!(board.get((0,2)),BlockColour::Red);
+        assert_eq!(board.row(3),0b1110);
+        assert_eq!(board.highest_block(),Some(2));
+
+        board.set((0,3),BlockColour::Blue);
+        let cleared = board.clean_screen();
+        assert_eq!((cleared.lines,cleared.garbage),(1,1));
+        assert_eq!(board.get((0,3)),BlockColour::Red);
+
+        board.set((0,0),BlockColour::Red);
+        assert!(!board.insert_row(0,BlockColour::Grey));
+    }
+}
@@ -0,0 +1,492 @@
+//! fumen, the board diagram encoding used by fumen.zui.jp and most Tetris tools
+//! only version 115 is understood, codes look like `v115@vhAAgH`
+//!
+//! a fumen field is 10 wide and 23 rows tall with a garbage row under the floor,
+//! rows here are counted up from the floor as fumen does
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::board::{Board, BlockColour};
+use crate::tetromino::{PieceKind, Rotation, Tetromino};
+
+/// digits of every number, least significant first
+const TABLE: &[u8;64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+/// characters comments are written with, after escaping
+const COMMENT_TABLE: &[u8] = b" !\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+
+pub const WIDTH: usize = 10;
+/// rows above the floor
+pub const HEIGHT: usize = 23;
+/// cells including the garbage row
+const CELLS: usize = WIDTH * (HEIGHT + 1);
+/// field value meaning nothing changed from the last page
+const UNCHANGED: u32 = (8 * CELLS as u32) + CELLS as u32 - 1;
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum FumenError{
+    /// not a version 115 code
+    Version,
+    /// a character outside the encoding
+    BadChar(char),
+    /// the code ends part way through a page
+    Truncated,
+    /// a value that can't be in a valid code
+    Invalid,
+    /// the board isn't [WIDTH] wide, is taller than [HEIGHT] or the piece is outside it
+    BoardSize,
+}
+
+impl core::fmt::Display for FumenError{
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result{
+        match self {
+            FumenError::Version => write!(f,"not a v115 fumen"),
+            FumenError::BadChar(c) => write!(f,"unknown '{}' in fumen",c),
+            FumenError::Truncated => write!(f,"fumen ends early"),
+            FumenError::Invalid => write!(f,"invalid fumen"),
+            FumenError::BoardSize => write!(f,"board doesn't fit a fumen field"),
+        }
+    }
+}
+
+/// a piece on a page, placed by the centre it rotates around
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct Piece{
+    pub kind: PieceKind,
+    pub rotation: Rotation,
+    /// column and row up from the floor of the centre
+    pub x: isize,
+    pub y: isize,
+}
+
+impl Piece{
+    /// the piece as placed on `board`, `None` if it doesn't fit a fumen field
+    pub fn from_tetromino(tet: &Tetromino, board: &Board) -> Option<Self>{
+        let bottom = board.height() as isize - 1;
+        let mut piece = Self{ kind: tet.kind(), rotation: tet.rotation(), x: 0, y: 0 };
+        //line the corners of the two shapes up, they are the same cells
+        let corner = |cells: &mut dyn Iterator<Item = (isize,isize)>| cells.fold((isize::MAX,isize::MAX),|(x,y),c| (x.min(c.0),y.min(c.1)));
+        let (x,y) = corner(&mut tet.blocks().map(|(x,y)| (x,bottom - y)));
+        let (ox,oy) = corner(&mut piece.cells().into_iter());
+        piece.x = x - ox;
+        piece.y = y - oy;
+        piece.position().map(|_| piece)
+    }
+
+    /// cells filled as columns and rows up from the floor
+    pub fn cells(&self) -> [(isize,isize);4]{
+        let offsets = match self.kind {
+            PieceKind::I => [(0,0),(-1,0),(1,0),(2,0)],
+            PieceKind::T => [(0,0),(-1,0),(1,0),(0,1)],
+            PieceKind::O => [(0,0),(1,0),(0,1),(1,1)],
+            PieceKind::L => [(0,0),(-1,0),(1,0),(1,1)],
+            PieceKind::J => [(0,0),(-1,0),(1,0),(-1,1)],
+            PieceKind::S => [(0,0),(-1,0),(0,1),(1,1)],
+            PieceKind::Z => [(0,0),(1,0),(0,1),(-1,1)],
+        };
+        offsets.map(|(x,y)| {
+            let (x,y) = match self.rotation {
+                Rotation::Spawn => (x,y),
+                Rotation::Right => (y,-x),
+                Rotation::Reverse => (-x,-y),
+                Rotation::Left => (-y,x),
+            };
+            (self.x + x,self.y + y)
+        })
+    }
+
+    /// fumen stores some pieces a cell away from their centre
+    fn shift(kind: PieceKind, rotation: Rotation) -> (isize,isize){
+        match (kind,rotation) {
+            (PieceKind::O,Rotation::Left) => (1,-1),
+            (PieceKind::O,Rotation::Reverse) => (1,0),
+            (PieceKind::O,Rotation::Spawn) => (0,-1),
+            (PieceKind::I,Rotation::Reverse) => (1,0),
+            (PieceKind::I,Rotation::Left) => (0,-1),
+            (PieceKind::S,Rotation::Spawn) => (0,-1),
+            (PieceKind::S,Rotation::Right) => (-1,0),
+            (PieceKind::Z,Rotation::Spawn) => (0,-1),
+            (PieceKind::Z,Rotation::Left) => (1,0),
+            _ => (0,0),
+        }
+    }
+
+    /// cell index the piece is stored at
+    fn position(&self) -> Option<u32>{
+        let (dx,dy) = Self::shift(self.kind,self.rotation);
+        let (x,y) = (self.x - dx,self.y - dy);
+        if self.cells().iter().any(|(x,y)| !(0..WIDTH as isize).contains(x) || !(-1..HEIGHT as isize).contains(y)) {
+            return None
+        }
+        Some(((HEIGHT as isize - y - 1) * WIDTH as isize + x) as u32)
+    }
+
+    fn from_position(kind: PieceKind, rotation: Rotation, position: u32) -> Self{
+        let (dx,dy) = Self::shift(kind,rotation);
+        let x = (position as usize % WIDTH) as isize;
+        let y = HEIGHT as isize - (position as usize / WIDTH) as isize - 1;
+        Self{ kind, rotation, x: x + dx, y: y + dy }
+    }
+}
+
+/// one diagram of a fumen
+#[derive(Debug,Clone,PartialEq)]
+pub struct Page{
+    /// [HEIGHT] rows of [WIDTH] top row first, the garbage row isn't kept
+    pub rows: Vec<Vec<BlockColour>>,
+    /// piece shown over the field
+    pub piece: Option<Piece>,
+    /// carried over from earlier pages if this one has none
+    pub comment: String,
+}
+
+impl Page{
+    /// pieces of a quiz comment like `#Q=[S](T)IJLOZ`, the held piece first then the current one
+    pub fn quiz(&self) -> Option<Vec<PieceKind>>{
+        let pieces = self.comment.strip_prefix("#Q=")?;
+        Some(pieces.chars().filter_map(PieceKind::from_letter).collect())
+    }
+}
+
+fn piece_number(kind: PieceKind) -> u32{
+    match kind {
+        PieceKind::I => 1,
+        PieceKind::L => 2,
+        PieceKind::O => 3,
+        PieceKind::Z => 4,
+        PieceKind::T => 5,
+        PieceKind::J => 6,
+        PieceKind::S => 7,
+    }
+}
+
+fn number_piece(number: u32) -> Option<PieceKind>{
+    PieceKind::ALL.into_iter().find(|kind| piece_number(*kind) == number)
+}
+
+/// grey is 8, pieces have their own numbers
+fn colour_number(colour: BlockColour) -> u32{
+    match colour {
+        BlockColour::None => 0,
+        BlockColour::Grey => 8,
+        colour => PieceKind::ALL.into_iter().find(|kind| kind.def().colour == colour).map_or(8,piece_number),
+    }
+}
+
+fn number_colour(number: u32) -> BlockColour{
+    match number_piece(number) {
+        Some(kind) => kind.def().colour,
+        None if number == 0 => BlockColour::None,
+        None => BlockColour::Grey,
+    }
+}
+
+fn rotation_number(rotation: Rotation) -> u32{
+    match rotation {
+        Rotation::Reverse => 0,
+        Rotation::Right => 1,
+        Rotation::Spawn => 2,
+        Rotation::Left => 3,
+    }
+}
+
+fn number_rotation(number: u32) -> Rotation{
+    match number {
+        0 => Rotation::Reverse,
+        1 => Rotation::Right,
+        2 => Rotation::Spawn,
+        _ => Rotation::Left,
+    }
+}
+
+/// writes `value` as `digits` characters
+fn push(code: &mut String, value: u32, digits: u32){
+    let mut value = value;
+    for _ in 0..digits {
+        code.push(TABLE[(value % 64) as usize] as char);
+        value /= 64;
+    }
+}
+
+/// `board` with `piece` over it as a single page code
+/// the board must be [WIDTH] wide and at most [HEIGHT] tall, it is put on the fumen floor
+pub fn encode(board: &Board, piece: Option<&Tetromino>) -> Result<String, FumenError>{
+    if (board.width() != WIDTH) || (board.height() > HEIGHT) { return Err(FumenError::BoardSize) }
+
+    //cells from the top of the fumen field, the board sits at the bottom above the garbage row
+    let mut cells = [0;CELLS];
+    let top = HEIGHT - board.height();
+    for y in 0..board.height() {
+        for x in 0..WIDTH {
+            cells[((top + y) * WIDTH) + x] = colour_number(board.get((x,y)));
+        }
+    }
+
+    let mut data = String::new();
+    //runs of the same change from an empty field, which is the value plus 8
+    let mut start = 0;
+    while start < CELLS {
+        let end = (start..CELLS).find(|i| cells[*i] != cells[start]).unwrap_or(CELLS);
+        push(&mut data,((cells[start] + 8) * CELLS as u32) + (end - start - 1) as u32,2);
+        start = end;
+    }
+    if cells.iter().all(|c| *c == 0) {
+        push(&mut data,0,1); //no pages after this repeat the field
+    }
+
+    let (kind,rotation,position) = match piece {
+        Some(tet) => {
+            let piece = Piece::from_tetromino(tet,board).ok_or(FumenError::BoardSize)?;
+            (piece_number(piece.kind),rotation_number(piece.rotation),piece.position().ok_or(FumenError::BoardSize)?)
+        }
+        None => (0,0,0),
+    };
+    //flags are rise, mirror, colour, comment then not locking, only colour is set
+    let flags = 0b00100;
+    push(&mut data,kind + (8 * (rotation + (4 * (position + (CELLS as u32 * flags))))),3);
+
+    //long codes are split with '?' so they can be pasted into text
+    let mut code = String::from("v115@");
+    for (i,c) in data.chars().enumerate() {
+        if (i >= 42) && ((i - 42) % 47 == 0) {
+            code.push('?');
+        }
+        code.push(c);
+    }
+    Ok(code)
+}
+
+/// reads digits of a code without the version
+struct Reader<'a>{
+    data: core::iter::Peekable<core::str::Chars<'a>>,
+}
+
+impl Reader<'_>{
+    fn poll(&mut self, digits: u32) -> Result<u32, FumenError>{
+        let mut value = 0;
+        let mut scale = 1;
+        for _ in 0..digits {
+            let c = self.data.next().ok_or(FumenError::Truncated)?;
+            let digit = TABLE.iter().position(|t| *t as char == c).ok_or(FumenError::BadChar(c))?;
+            value += digit as u32 * scale;
+            scale *= 64;
+        }
+        Ok(value)
+    }
+
+    fn is_empty(&mut self) -> bool{
+        self.data.peek().is_none()
+    }
+}
+
+/// undoes javascript's `escape`, `%XX` and `%uXXXX`
+fn unescape(text: &str) -> String{
+    let mut result = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue
+        }
+        let rest = chars.as_str();
+        let (digits,len) = match rest.strip_prefix('u') {
+            Some(hex) => (hex.get(..4),5),
+            None => (rest.get(..2),2),
+        };
+        match digits.and_then(|d| u32::from_str_radix(d,16).ok()).and_then(char::from_u32) {
+            Some(decoded) => {
+                result.push(decoded);
+                chars = rest[len..].chars();
+            }
+            None => result.push(c),
+        }
+    }
+    result
+}
+
+/// every page of a code, anything before the version like a url is skipped
+pub fn decode(code: &str) -> Result<Vec<Page>, FumenError>{
+    let start = code.find("115@").ok_or(FumenError::Version)?;
+    let data: String = code[start + 4..].chars().filter(|c| (*c != '?') && !c.is_whitespace()).collect();
+    let mut reader = Reader{ data: data.chars().peekable() };
+
+    let mut pages = Vec::new();
+    let mut field = [0u32;CELLS];
+    let mut repeat = 0;
+    let mut comment = String::new();
+    while !reader.is_empty() {
+        if repeat > 0 {
+            repeat -= 1;
+        } else {
+            let mut index = 0;
+            let mut changed = false;
+            while index < CELLS {
+                let value = reader.poll(2)?;
+                let (diff,count) = (value / CELLS as u32,(value % CELLS as u32) as usize + 1);
+                if index + count > CELLS { return Err(FumenError::Invalid) }
+                changed |= value != UNCHANGED;
+                for cell in &mut field[index..index + count] {
+                    *cell = (*cell + diff).checked_sub(8).filter(|c| *c <= 8).ok_or(FumenError::Invalid)?;
+                }
+                index += count;
+            }
+            if !changed {
+                repeat = reader.poll(1)?;
+            }
+        }
+
+        let mut action = reader.poll(3)?;
+        let kind = number_piece(action % 8);
+        action /= 8;
+        let rotation = number_rotation(action % 4);
+        action /= 4;
+        let position = action % CELLS as u32;
+        action /= CELLS as u32;
+        let (rise,mirror,has_comment,lock) = (action & 1 != 0,action & 2 != 0,action & 8 != 0,action & 16 == 0);
+
+        if has_comment {
+            let len = reader.poll(2)? as usize;
+            let mut escaped = String::new();
+            for _ in 0..len.div_ceil(4) {
+                let mut value = reader.poll(5)?;
+                for _ in 0..4 {
+                    escaped.push(COMMENT_TABLE[(value % 96) as usize % COMMENT_TABLE.len()] as char);
+                    value /= 96;
+                }
+            }
+            escaped.truncate(len);
+            comment = unescape(&escaped);
+        }
+
+        let piece = kind.map(|kind| Piece::from_position(kind,rotation,position));
+        pages.push(Page{
+            rows: field[..HEIGHT * WIDTH].chunks(WIDTH).map(|row| row.iter().map(|c| number_colour(*c)).collect()).collect(),
+            piece,
+            comment: comment.clone(),
+        });
+
+        //the next page starts from this one after the piece locks
+        if lock {
+            if let Some(piece) = piece {
+                for (x,y) in piece.cells() {
+                    let row = HEIGHT as isize - y - 1;
+                    if (0..WIDTH as isize).contains(&x) && (0..=HEIGHT as isize).contains(&row) {
+                        field[(row as usize * WIDTH) + x as usize] = piece_number(piece.kind);
+                    }
+                }
+            }
+            //full rows above the garbage row are cleared
+            let mut rows: Vec<[u32;WIDTH]> = field[..HEIGHT * WIDTH].chunks(WIDTH)
+                .map(|row| row.try_into().unwrap())
+                .filter(|row: &[u32;WIDTH]| row.contains(&0))
+                .collect();
+            while rows.len() < HEIGHT {
+                rows.insert(0,[0;WIDTH]);
+            }
+            for (i,row) in rows.iter().enumerate() {
+                field[i * WIDTH..(i + 1) * WIDTH].copy_from_slice(row);
+            }
+            if rise {
+                field.copy_within(WIDTH..,0);
+                field[HEIGHT * WIDTH..].fill(0);
+            }
+            if mirror {
+                for row in field[..HEIGHT * WIDTH].chunks_mut(WIDTH) {
+                    row.reverse();
+                }
+            }
+        }
+    }
+    Ok(pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Game, Config, Action};
+
+    #[test]
+    fn empty_field() {
+        let board = Board::new(WIDTH,Board::GAME_HEIGHT,Board::HIDDEN_ROWS);
+        assert_eq!(encode(&board,None).unwrap(),"v115@vhAAgH");
+
+        let pages = decode("https://fumen.zui.jp/?v115@vhAAgH").unwrap();
+        assert_eq!(pages.len(),1);
+        assert!(pages[0].rows.iter().flatten().all(|c| *c == BlockColour::None));
+        assert_eq!(pages[0].piece,None);
+        assert_eq!(decode("vhAAgH"),Err(FumenError::Version));
+        assert_eq!(decode("v115@vh"),Err(FumenError::Truncated));
+    }
+
+    #[test]
+    fn boards_and_pieces_survive_a_round_trip() {
+        let mut game = Game::new(&Config::default(),0);
+        for (kind,action) in [(PieceKind::I,Action::MoveLeft),(PieceKind::O,Action::MoveRight),(PieceKind::T,Action::RotateRight)] {
+            game.spawn(kind);
+            for _ in 0..4 {
+                game.action(action);
+            }
+            game.action(Action::HardDrop);
+        }
+        game.board_mut().set((9,Board::GAME_HEIGHT + Board::HIDDEN_ROWS - 1),BlockColour::Grey);
+
+        for kind in PieceKind::ALL {
+            for turns in 0..4 {
+                game.spawn(kind);
+                for _ in 0..turns {
+                    game.action(Action::RotateLeft);
+                }
+                let tet = *game.active().unwrap();
+                let code = encode(game.board(),Some(&tet)).unwrap();
+                let page = &decode(&code).unwrap()[0];
+
+                let top = HEIGHT - game.board().height();
+                for (y,row) in page.rows.iter().enumerate() {
+                    for (x,colour) in row.iter().enumerate() {
+                        let expected = if y < top { BlockColour::None } else { game.board().get((x,y - top)) };
+                        assert_eq!(*colour,expected);
+                    }
+                }
+
+                let piece = page.piece.unwrap();
+                assert_eq!((piece.kind,piece.rotation),(kind,tet.rotation()));
+                let mut cells = piece.cells().map(|(x,y)| (x,(HEIGHT - top) as isize - 1 - y));
+                let mut blocks: Vec<_> = tet.blocks().collect();
+                cells.sort();
+                blocks.sort();
+                assert_eq!(cells[..],blocks[..],"{:?} {}",kind,code);
+            }
+        }
+    }
+
+    #[test]
+    fn pages_lock_pieces_into_the_next() {
+        //an I on the floor then a page with a comment, encoded by hand
+        //page 1: empty field, I flat on the floor with its centre at x 1
+        //page 2: unchanged field, no piece, the comment "hi"
+        let mut data = String::new();
+        push(&mut data,UNCHANGED,2);
+        push(&mut data,0,1);
+        push(&mut data,1 + (8 * (2 + (4 * (221 + (CELLS as u32 * 0b00100))))),3);
+        push(&mut data,UNCHANGED,2);
+        push(&mut data,0,1);
+        push(&mut data,8 * 4 * CELLS as u32 * 0b01000,3);
+        push(&mut data,2,2);
+        let h = COMMENT_TABLE.iter().position(|c| *c == b'h').unwrap() as u32;
+        let i = COMMENT_TABLE.iter().position(|c| *c == b'i').unwrap() as u32;
+        push(&mut data,h + (i * 96),5);
+
+        let pages = decode(&alloc::format!("v115@{}",data)).unwrap();
+        assert_eq!(pages.len(),2);
+        assert_eq!(pages[0].piece.unwrap().cells(),[(1,0),(0,0),(2,0),(3,0)]);
+        assert_eq!(pages[0].comment,"");
+        assert_eq!(pages[1].rows[HEIGHT - 1][..4],[BlockColour::Cyan;4]);
+        assert_eq!(pages[1].comment,"hi");
+    }
+
+    #[test]
+    fn quiz_comments_list_pieces() {
+        let page = Page{ rows: Vec::new(), piece: None, comment: unescape("%23Q%3D%5BS%5D%28T%29IJ") };
+        assert_eq!(page.comment,"#Q=[S](T)IJ");
+        assert_eq!(page.quiz().unwrap(),[PieceKind::S,PieceKind::T,PieceKind::I,PieceKind::J]);
+    }
+}
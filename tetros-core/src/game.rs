@@ -0,0 +1,931 @@
+use core::time::Duration;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+use crate::board::{Board, BlockColour, ClearResult};
+use crate::tetromino::{Tetromino, PieceKind};
+use crate::randomizer::{Randomizer, RandomizerKind};
+use crate::scoring::{self, TSpin};
+use crate::gravity::GravityCurve;
+use crate::lock::{LockDelay, LockState};
+
+/// player inputs understood by the game
+/// frontends translate their own key codes into these
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Action{
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    RotateRight,
+    RotateLeft,
+    Hold,
+    HardDrop,
+}
+
+/// something that happened that the player may want to be told about
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Event{
+    /// a piece locked clearing rows or with a T-spin
+    /// `back_to_back` is set when the clear continued a back-to-back chain
+    Clear{ lines: usize, spin: TSpin, back_to_back: bool },
+}
+
+impl core::fmt::Display for Event{
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result{
+        match self {
+            Event::Clear{ lines, spin, back_to_back } => {
+                if *back_to_back {
+                    write!(f,"B2B ")?;
+                }
+                match spin {
+                    TSpin::None => {}
+                    TSpin::Mini => write!(f,"Mini T-Spin")?,
+                    TSpin::Full => write!(f,"T-Spin")?,
+                }
+                if (*spin != TSpin::None) && (*lines > 0) {
+                    write!(f," ")?;
+                }
+                match lines {
+                    0 => Ok(()),
+                    1 => write!(f,"Single"),
+                    2 => write!(f,"Double"),
+                    3 => write!(f,"Triple"),
+                    _ => write!(f,"Tetris"),
+                }
+            }
+        }
+    }
+}
+
+/// counts of what the player did, for end of game statistics
+#[derive(Debug,Clone,Copy,PartialEq,Default)]
+pub struct Stats{
+    /// locks that cleared 1 to 4 rows, indexed by rows minus one
+    pub clears: [usize;4],
+    /// T-spins including minis and ones that cleared nothing
+    pub t_spins: usize,
+    /// clears that continued a back-to-back chain
+    pub back_to_backs: usize,
+    pub max_combo: u32,
+}
+
+/// settings that may differ between games
+#[derive(Debug,Clone)]
+pub struct Config{
+    /// columns, clamped to `Game::MIN_WIDTH..=Board::MAX_WIDTH`
+    pub width: usize,
+    /// visible rows
+    pub height: usize,
+    /// buffer rows above the visible ones
+    pub hidden: usize,
+    /// number of upcoming pieces shown, clamped to `1..=Game::MAX_PREVIEW`
+    pub preview: usize,
+    pub randomizer: RandomizerKind,
+    pub start_level: u32,
+    /// lines needed to go up a level
+    pub lines_per_level: usize,
+    pub gravity: GravityCurve,
+    pub lock_delay: LockDelay,
+    /// game time after which the game stops, for timed modes
+    pub time_limit: Option<Duration>,
+    /// garbage rows the board starts with
+    pub garbage: usize,
+    /// time between garbage rows rising from the bottom
+    pub garbage_rise: Option<Duration>,
+    /// pieces dealt in order instead of using the randomizer, nothing comes after the last
+    /// the randomizer is used when this is empty
+    pub sequence: Vec<PieceKind>,
+}
+
+impl Default for Config{
+    fn default() -> Self{
+        Self{
+            width: Board::GAME_WIDTH,
+            height: Board::GAME_HEIGHT,
+            hidden: Board::HIDDEN_ROWS,
+            preview: 5,
+            randomizer: RandomizerKind::default(),
+            start_level: 1,
+            lines_per_level: 10,
+            gravity: GravityCurve::default(),
+            lock_delay: LockDelay::default(),
+            time_limit: None,
+            garbage: 0,
+            garbage_rise: None,
+            sequence: Vec::new(),
+        }
+    }
+}
+
+/// state of a single game
+pub struct Game{
+    board: Board,
+    active: Option<Tetromino>,
+    lines: usize,
+    score: u64,
+    level: u32,
+    start_level: u32,
+    lines_per_level: usize,
+
+    gravity: GravityCurve,
+    fall_progress: Duration, //time towards the next row of gravity
+    lock_delay: LockDelay,
+    lock_state: LockState,
+
+    randomizer: Box<dyn Randomizer>,
+    queue: VecDeque<PieceKind>, //upcoming pieces, always `preview` long unless `fixed`
+    fixed: bool, //pieces only come from `Config::sequence`
+    preview: usize,
+    hold: Option<PieceKind>,
+    can_hold: bool, //cleared by holding, set again when a piece locks
+
+    combo: Option<u32>, //clearing locks in a row minus one, `None` once a lock clears nothing
+    back_to_back: bool, //last clear was difficult
+
+    events: Vec<Event>, //not yet collected by `take_events`
+    lost: bool,
+    stats: Stats,
+
+    time: Duration, //total passed to `advance`, never more than `time_limit`
+    time_limit: Option<Duration>,
+
+    garbage_rng: SmallRng, //picks holes, separate from the randomizer so pieces don't depend on garbage
+    garbage_rise: Option<Duration>,
+    rise_progress: Duration, //time towards the next garbage row
+    garbage_cleared: usize,
+    pieces: usize, //locked so far
+    inputs: usize, //calls to `action`
+}
+
+impl Game{
+    pub const MAX_PREVIEW: usize = 6;
+    /// narrowest board every piece can spawn on
+    pub const MIN_WIDTH: usize = 4;
+    /// fewest visible rows, enough for a piece to spawn and be turned
+    pub const MIN_HEIGHT: usize = 4;
+
+    /// `seed` is passed to the randomizer
+    pub fn new(config: &Config, seed: u64) -> Self{
+        let preview = config.preview.clamp(1,Self::MAX_PREVIEW);
+        let mut randomizer = config.randomizer.build(seed);
+        let fixed = !config.sequence.is_empty();
+        let queue = match fixed {
+            true => config.sequence.iter().copied().collect(),
+            false => (0..preview).map(|_| randomizer.next_piece()).collect(),
+        };
+        let board = Board::new(
+            config.width.clamp(Self::MIN_WIDTH,Board::MAX_WIDTH),
+            config.height.max(Self::MIN_HEIGHT),
+            config.hidden,
+        );
+
+        let mut game = Self{
+            lock_state: LockState::new(config.lock_delay,Self::spawn_row(&board)),
+            board,
+            active: None,
+            lines: 0,
+            score: 0,
+            level: config.start_level.max(1),
+            start_level: config.start_level.max(1),
+            lines_per_level: config.lines_per_level.max(1),
+            gravity: config.gravity,
+            fall_progress: Duration::ZERO,
+            lock_delay: config.lock_delay,
+            randomizer,
+            queue,
+            fixed,
+            preview,
+            hold: None,
+            can_hold: true,
+            combo: None,
+            back_to_back: false,
+            events: Vec::new(),
+            lost: false,
+            stats: Stats::default(),
+            time: Duration::ZERO,
+            time_limit: config.time_limit,
+            garbage_rng: SmallRng::seed_from_u64(!seed),
+            garbage_rise: config.garbage_rise.filter(|rise| !rise.is_zero()),
+            rise_progress: Duration::ZERO,
+            garbage_cleared: 0,
+            pieces: 0,
+            inputs: 0,
+        };
+        game.add_garbage(config.garbage);
+        game
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// for modes that set up the board before the first piece
+    pub fn board_mut(&mut self) -> &mut Board {
+        &mut self.board
+    }
+
+    /// currently falling tetromino if there is one
+    pub fn active(&self) -> Option<&Tetromino> {
+        self.active.as_ref()
+    }
+
+    /// game time, the sum of everything passed to [Self::advance]
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// time until the limit in [Config::time_limit] is reached
+    pub fn time_left(&self) -> Option<Duration> {
+        self.time_limit.map(|limit| limit.saturating_sub(self.time))
+    }
+
+    /// true once the time limit has been reached, the game stops where it is
+    pub fn is_timed_out(&self) -> bool {
+        self.time_left() == Some(Duration::ZERO)
+    }
+
+    /// rows with garbage in them that have been cleared
+    pub fn garbage_cleared(&self) -> usize {
+        self.garbage_cleared
+    }
+
+    /// pushes `rows` rows of garbage in from the bottom, each with one random hole
+    /// the falling piece is moved up out of the way
+    /// the game is lost if the stack is pushed into the hidden rows
+    pub fn add_garbage(&mut self, rows: usize){
+        let full = ((1u32 << self.board.width()) - 1) as u16;
+        for _ in 0..rows {
+            let hole = self.garbage_rng.gen_range(0..self.board.width());
+            let fits = self.board.insert_row(full & !(1 << hole),BlockColour::Grey);
+            if !fits || self.board.highest_block().is_some_and(|y| y < self.board.hidden()) {
+                self.lost = true;
+            }
+            if let Some(tet) = &mut self.active {
+                if !tet.is_legal(&self.board) {
+                    tet.location.1 -= 1;
+                }
+                if !tet.is_legal(&self.board) {
+                    self.lost = true;
+                }
+            }
+            if self.lost {
+                self.active = None;
+                return
+            }
+        }
+    }
+
+    /// pieces locked so far
+    pub fn pieces(&self) -> usize {
+        self.pieces
+    }
+
+    /// number of actions the player has tried, including ones that failed
+    pub fn inputs(&self) -> usize {
+        self.inputs
+    }
+
+    /// total number of cleared lines
+    pub fn lines(&self) -> usize {
+        self.lines
+    }
+
+    pub fn score(&self) -> u64 {
+        self.score
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    /// copy of the falling piece where it would land
+    pub fn ghost(&self) -> Option<Tetromino> {
+        let mut ghost = self.active?;
+        ghost.location.1 += ghost.drop_distance(&self.board);
+        Some(ghost)
+    }
+
+    /// current combo, 1 on the second clearing lock in a row
+    /// `None` or 0 means there is no combo
+    pub fn combo(&self) -> Option<u32> {
+        self.combo
+    }
+
+    /// counts of clears, T-spins and combos so far
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// true if the next difficult clear will get the back-to-back bonus
+    pub fn back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+
+    /// returns everything that happened since the last call
+    pub fn take_events(&mut self) -> Vec<Event> {
+        core::mem::take(&mut self.events)
+    }
+
+    /// piece in the hold slot
+    pub fn held(&self) -> Option<PieceKind> {
+        self.hold
+    }
+
+    /// left of a spawned piece's bounding box, [crate::PieceDef::spawn_column] moved to stay centred
+    fn spawn_column(board: &Board, kind: PieceKind) -> isize {
+        let offset = (board.width() as isize - Board::GAME_WIDTH as isize) / 2;
+        kind.def().spawn_column + offset
+    }
+
+    /// top of a spawned piece's bounding box, one row into the hidden rows
+    /// so that every piece has a block in the visible field
+    fn spawn_row(board: &Board) -> isize {
+        board.hidden().saturating_sub(1) as isize
+    }
+
+    /// places a new `kind` at the top of the board as the falling piece
+    /// if it overlaps the stack the game is lost (block out)
+    pub fn spawn(&mut self, kind: PieceKind){
+        let mut tet = Tetromino::from_kind(kind);
+        tet.location = (Self::spawn_column(&self.board,kind),Self::spawn_row(&self.board));
+        self.fall_progress = Duration::ZERO;
+        self.lock_state = LockState::new(self.lock_delay,tet.location.1);
+        if !tet.is_legal(&self.board) {
+            self.lost = true;
+            self.active = None;
+            return
+        }
+        self.active = Some(tet);
+    }
+
+    /// upcoming pieces, the front is spawned next
+    /// with a fixed [Config::sequence] this is every piece left, not just the [Self::preview]
+    pub fn queue(&self) -> &VecDeque<PieceKind> {
+        &self.queue
+    }
+
+    /// number of pieces kept in the queue
+    pub fn preview(&self) -> usize {
+        self.preview
+    }
+
+    /// spawns the front of the queue and refills it from the randomizer
+    /// once a fixed sequence runs out the held piece is spawned, then nothing
+    pub fn spawn_next(&mut self){
+        if self.is_timed_out() { return }
+        if !self.fixed {
+            self.queue.push_back(self.randomizer.next_piece());
+        }
+        match self.queue.pop_front().or_else(|| self.hold.take()) {
+            Some(kind) => self.spawn(kind),
+            None => self.active = None,
+        }
+    }
+
+    /// true if pieces come from [Config::sequence] rather than the randomizer
+    pub fn is_fixed(&self) -> bool{
+        self.fixed
+    }
+
+    /// true once every piece of a fixed sequence has been used
+    pub fn is_out_of_pieces(&self) -> bool{
+        self.fixed && self.queue.is_empty() && self.hold.is_none() && self.active.is_none()
+    }
+
+    /// swaps the falling piece with the held one,
+    /// when nothing is held the next piece is spawned instead
+    /// only allowed once until the next piece locks,
+    /// and not when a fixed sequence has nothing left to spawn
+    pub fn hold(&mut self) -> bool{
+        if !self.can_hold { return false }
+        let current = match &self.active {
+            Some(tet) => tet.kind(),
+            None => return false,
+        };
+        //the held piece would come straight back out
+        if self.fixed && self.queue.is_empty() && self.hold.is_none() { return false }
+
+        match self.hold.replace(current) {
+            Some(kind) => self.spawn(kind),
+            None => self.spawn_next(),
+        }
+        self.can_hold = false;
+        true
+    }
+
+    /// applies `action` to the falling piece
+    /// returns true if anything moved
+    pub fn action(&mut self, action: Action) -> bool{
+        if self.is_timed_out() { return false }
+        self.inputs += 1;
+        match action {
+            Action::Hold => return self.hold(),
+            Action::HardDrop => return self.hard_drop(),
+            _ => {}
+        }
+
+        let board = &self.board;
+        let tet = match &mut self.active {
+            Some(t) => t,
+            None => return false,
+        };
+
+        let moved = match action {
+            Action::MoveLeft => tet.legal_move((-1,0),board),
+            Action::MoveRight => tet.legal_move((1,0),board),
+            Action::SoftDrop => {
+                let moved = tet.legal_move((0,1),board);
+                if moved {
+                    self.score += scoring::SOFT_DROP;
+                    self.lock_state.stepped(tet.location.1);
+                }
+                moved
+            }
+            Action::RotateRight => tet.safe_ror(board),
+            Action::RotateLeft => tet.safe_rol(board),
+            Action::Hold | Action::HardDrop => unreachable!(),
+        };
+
+        if moved {
+            self.lock_state.moved();
+        }
+        moved
+    }
+
+    /// drops the falling piece as far as it will go and locks it straight away
+    pub fn hard_drop(&mut self) -> bool{
+        let tet = match &mut self.active {
+            Some(t) => t,
+            None => return false,
+        };
+
+        let rows = tet.drop_distance(&self.board);
+        tet.fall(rows);
+        self.score += scoring::HARD_DROP * rows as u64;
+        self.lock();
+        true
+    }
+
+    /// moves the game forward by `elapsed`, letting the falling piece drop under gravity
+    /// a piece resting on the stack locks once its lock delay runs out
+    /// garbage rises if [Config::garbage_rise] is set
+    /// returns false once the piece has locked or the time limit is reached
+    /// a piece still falling at the time limit is left unlocked
+    pub fn advance(&mut self, elapsed: Duration) -> bool{
+        if self.is_timed_out() { return false }
+        let elapsed = self.time_left().map_or(elapsed,|left| elapsed.min(left));
+        self.time += elapsed;
+        if let Some(rise) = self.garbage_rise {
+            self.rise_progress += elapsed;
+            while self.rise_progress >= rise {
+                self.rise_progress -= rise;
+                self.add_garbage(1);
+            }
+        }
+        let tet = match &mut self.active {
+            Some(t) => t,
+            None => return false,
+        };
+
+        self.fall_progress += elapsed;
+        let distance = tet.drop_distance(&self.board);
+        let rows = match self.gravity.gravity(self.level).rows(&mut self.fall_progress) {
+            Some(rows) => (rows as isize).min(distance),
+            None => {
+                //instant, fall as far as possible
+                self.fall_progress = Duration::ZERO;
+                distance
+            }
+        };
+
+        tet.fall(rows);
+        self.lock_state.stepped(tet.location.1);
+
+        //the delay starts counting once the piece is resting on the stack
+        if (distance == 0) && self.lock_state.grounded(elapsed) {
+            self.lock();
+            return false
+        }
+        !self.is_timed_out()
+    }
+
+    /// writes the falling piece into the board, clears full rows and scores them
+    fn lock(&mut self) -> ClearResult{
+        let mut result = ClearResult::default();
+        if let Some(tet) = self.active.take() {
+            //corners have to be checked before the rows are removed
+            let spin = tet.t_spin(&self.board);
+            //locking entirely inside the hidden rows is a lock out
+            let hidden = self.board.hidden() as isize;
+            if tet.blocks().all(|(_,y)| y < hidden) {
+                self.lost = true;
+            }
+            tet.set(&mut self.board);
+            self.pieces += 1;
+            result = self.board.clean_screen();
+
+            let mut points = scoring::line_clear(result.lines,spin,self.level);
+            let mut back_to_back = false;
+            if result.lines > 0 {
+                let difficult = scoring::is_difficult(result.lines,spin);
+                back_to_back = difficult && self.back_to_back;
+                if back_to_back {
+                    points = scoring::back_to_back(points);
+                }
+                self.back_to_back = difficult;
+
+                let combo = self.combo.map_or(0,|c| c + 1);
+                points += scoring::combo(combo,self.level);
+                self.combo = Some(combo);
+                self.stats.clears[result.lines.min(4) - 1] += 1;
+                self.stats.max_combo = self.stats.max_combo.max(combo);
+                self.stats.back_to_backs += back_to_back as usize;
+            } else {
+                //a T-spin without lines doesn't break back-to-back
+                self.combo = None;
+            }
+            if spin != TSpin::None {
+                self.stats.t_spins += 1;
+            }
+            if (result.lines > 0) || (spin != TSpin::None) {
+                self.events.push(Event::Clear{ lines: result.lines, spin, back_to_back });
+            }
+            self.score += points;
+            self.lines += result.lines;
+            self.garbage_cleared += result.garbage;
+            self.level = self.level.max(self.start_level + (self.lines / self.lines_per_level) as u32);
+        }
+        self.can_hold = true;
+        result
+    }
+
+    /// true after a block out or lock out, no more pieces should be spawned
+    pub fn is_lost(&self) -> bool{
+        self.lost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::BlockColour;
+
+    /// rows including the hidden ones
+    const HEIGHT: usize = Board::GAME_HEIGHT + Board::HIDDEN_ROWS;
+
+    fn new_game() -> Game {
+        Game::new(&Config::default(),0)
+    }
+
+    #[test]
+    fn piece_falls_then_locks_on_floor() {
+        let mut game = new_game();
+        game.spawn(PieceKind::O);
+
+        //spawns one row down
+        for _ in 0..HEIGHT - 3 {
+            assert!(game.advance(Duration::from_secs(1)));
+        }
+        //lock delay
+        assert!(game.advance(Duration::from_millis(499)));
+        assert!(game.action(Action::MoveRight));
+        assert!(game.advance(Duration::from_millis(499)));
+        assert!(!game.advance(Duration::from_millis(1)));
+        assert!(game.active().is_none());
+        assert_eq!(game.board().get((5,HEIGHT - 1)),BlockColour::Yellow);
+        assert!(!game.is_lost());
+    }
+
+    #[test]
+    fn gravity_drops_rows_over_time() {
+        let mut game = new_game();
+        game.spawn(PieceKind::O);
+
+        assert!(game.advance(Duration::from_millis(999)));
+        assert_eq!(game.active().unwrap().location.1,1);
+        assert!(game.advance(Duration::from_millis(1)));
+        assert_eq!(game.active().unwrap().location.1,2);
+        assert!(game.advance(Duration::from_millis(3_500)));
+        assert_eq!(game.active().unwrap().location.1,5);
+
+        //runs out of room and locks
+        assert!(game.advance(Duration::from_secs(60)));
+        assert!(!game.advance(Duration::from_secs(1)));
+        assert_eq!(game.board().get((4,HEIGHT - 1)),BlockColour::Yellow);
+    }
+
+    #[test]
+    fn level_up_every_ten_lines() {
+        let config = Config{ start_level: 3, ..Config::default() };
+        let mut game = Game::new(&config,0);
+        for i in 0..25 {
+            game.board = Board::new(Board::GAME_WIDTH,Board::GAME_HEIGHT,Board::HIDDEN_ROWS);
+            for x in 0..Board::GAME_WIDTH - 1 {
+                game.board.set((x,HEIGHT - 1),BlockColour::Grey);
+            }
+            game.spawn(PieceKind::I);
+            game.action(Action::RotateRight);
+            for _ in 0..Board::GAME_WIDTH {
+                game.action(Action::MoveRight);
+            }
+            game.action(Action::HardDrop);
+            assert_eq!(game.lines(),i + 1);
+            assert_eq!(game.level(),3 + ((i as u32 + 1) / 10));
+        }
+    }
+
+    #[test]
+    fn hold_once_per_piece() {
+        let mut game = new_game();
+        game.spawn(PieceKind::T);
+
+        assert!(game.action(Action::Hold));
+        assert_eq!(game.held(),Some(PieceKind::T));
+        let next = game.active().unwrap().kind();
+
+        assert!(!game.action(Action::Hold));
+        assert_eq!(game.active().unwrap().kind(),next);
+
+        game.action(Action::HardDrop);
+        game.spawn(PieceKind::I);
+        assert!(game.action(Action::Hold));
+        assert_eq!(game.held(),Some(PieceKind::I));
+        assert_eq!(game.active().unwrap().kind(),PieceKind::T);
+    }
+
+    #[test]
+    fn queue_feeds_spawns() {
+        let config = Config{ preview: 3, ..Config::default() };
+        let mut game = Game::new(&config,7);
+        let mut bag = RandomizerKind::Bag.build(7);
+        let dealt: alloc::vec::Vec<_> = (0..30).map(|_| bag.next_piece()).collect();
+
+        for i in 0..20 {
+            assert!(game.queue().iter().eq(&dealt[i..i+3]));
+            game.spawn_next();
+            assert_eq!(game.active().unwrap().kind(),dealt[i]);
+        }
+    }
+
+    #[test]
+    fn hard_drop_locks_at_bottom() {
+        let mut game = new_game();
+        game.spawn(PieceKind::O);
+
+        assert!(game.action(Action::HardDrop));
+        assert!(game.active().is_none());
+        assert_eq!(game.board().get((4,HEIGHT - 1)),BlockColour::Yellow);
+        assert_eq!(game.score(),2 * (HEIGHT as u64 - 3));
+    }
+
+    #[test]
+    fn clearing_lines_scores() {
+        let mut game = new_game();
+        //leave a 2 wide gap at columns 4 and 5
+        for y in HEIGHT - 2..HEIGHT {
+            for x in (0..4).chain(6..Board::GAME_WIDTH) {
+                game.board.set((x,y),BlockColour::Grey);
+            }
+        }
+        game.spawn(PieceKind::O);
+        game.action(Action::SoftDrop);
+        game.action(Action::HardDrop);
+
+        assert_eq!(game.lines(),2);
+        let dropped = HEIGHT as u64 - 4;
+        assert_eq!(game.score(),1 + (2 * dropped) + 300);
+        assert!(game.board().is_free((0,HEIGHT as isize - 1)));
+    }
+
+    #[test]
+    fn back_to_back_tetris_combo() {
+        let mut game = new_game();
+        for i in 0..3 {
+            for y in HEIGHT - 4..HEIGHT {
+                for x in 0..Board::GAME_WIDTH - 1 {
+                    game.board.set((x,y),BlockColour::Grey);
+                }
+            }
+            game.spawn(PieceKind::I);
+            game.action(Action::RotateRight);
+            for _ in 0..Board::GAME_WIDTH {
+                game.action(Action::MoveRight);
+            }
+            let rows = game.ghost().unwrap().location.1 - game.active().unwrap().location.1;
+            let score = game.score() + (2 * rows as u64);
+            game.action(Action::HardDrop);
+
+            let (points,back_to_back) = match i {
+                0 => (800, false),
+                _ => (1200 + (50 * i), true),
+            };
+            assert_eq!(game.score() - score,points);
+            assert_eq!(game.combo(),Some(i as u32));
+            assert_eq!(game.take_events(),[Event::Clear{ lines: 4, spin: TSpin::None, back_to_back }]);
+        }
+
+        //nothing cleared breaks the combo but not back-to-back
+        game.spawn(PieceKind::O);
+        game.action(Action::HardDrop);
+        assert_eq!(game.combo(),None);
+        assert!(game.back_to_back());
+        assert_eq!(*game.stats(),Stats{ clears: [0,0,0,3], t_spins: 0, back_to_backs: 2, max_combo: 2 });
+    }
+
+    #[test]
+    fn block_out_when_spawning_on_the_stack() {
+        let mut game = new_game();
+        game.board.set((4,Board::HIDDEN_ROWS),BlockColour::Grey);
+        game.spawn(PieceKind::T);
+        assert!(game.is_lost());
+        assert!(game.active().is_none());
+        assert!(!game.advance(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn lock_out_above_the_visible_rows() {
+        let mut game = new_game();
+        game.spawn(PieceKind::O);
+        //a piece only partly above the visible rows is fine
+        game.board.set((0,Board::HIDDEN_ROWS + 1),BlockColour::Grey);
+        for _ in 0..3 {
+            assert!(game.action(Action::MoveLeft));
+        }
+        assert!(game.action(Action::HardDrop));
+        assert!(!game.is_lost());
+
+        game.spawn(PieceKind::O);
+        game.board.set((4,Board::HIDDEN_ROWS),BlockColour::Grey);
+        game.active.as_mut().unwrap().location = (3,0);
+        assert!(game.action(Action::HardDrop));
+        assert!(game.is_lost());
+    }
+
+    #[test]
+    fn zero_fixed_gravity_is_instant() {
+        let config = Config{ gravity: GravityCurve::Fixed(Duration::ZERO), ..Config::default() };
+        let mut game = Game::new(&config,0);
+        game.spawn(PieceKind::O);
+        assert!(game.advance(Duration::from_millis(1)));
+        assert_eq!(game.active().unwrap().drop_distance(game.board()),0);
+    }
+
+    #[test]
+    fn time_limit_stops_mid_piece() {
+        let config = Config{ time_limit: Some(Duration::from_secs(2)), ..Config::default() };
+        let mut game = Game::new(&config,0);
+        game.spawn(PieceKind::O);
+
+        assert!(game.advance(Duration::from_millis(1500)));
+        assert_eq!(game.time_left(),Some(Duration::from_millis(500)));
+        assert!(!game.advance(Duration::from_secs(5)));
+        assert!(game.is_timed_out());
+        assert_eq!(game.time(),Duration::from_secs(2));
+
+        //the piece is left where it was and nothing else happens
+        let location = game.active().unwrap().location;
+        assert!(!game.action(Action::HardDrop));
+        assert_eq!(game.active().unwrap().location,location);
+        assert_eq!(game.pieces(),0);
+        assert!(!game.is_lost());
+    }
+
+    #[test]
+    fn garbage_rises_under_the_piece() {
+        let config = Config{ garbage: 3, garbage_rise: Some(Duration::from_secs(1)), ..Config::default() };
+        let mut game = Game::new(&config,0);
+        for y in HEIGHT - 3..HEIGHT {
+            assert_eq!(game.board().row(y).count_ones() as usize,Board::GAME_WIDTH - 1);
+            let x = game.board().row(y).trailing_zeros() as usize;
+            assert_eq!(game.board().get((x,y)),BlockColour::Grey);
+        }
+        assert_eq!(game.board().highest_block(),Some(HEIGHT - 3));
+
+        game.spawn(PieceKind::O);
+        assert!(game.advance(Duration::from_secs(1)));
+        assert_eq!(game.board().highest_block(),Some(HEIGHT - 4));
+
+        //a piece resting on the garbage is pushed up with it
+        let tet = game.active.as_mut().unwrap();
+        tet.fall(tet.drop_distance(&game.board));
+        let y = tet.location.1;
+        game.add_garbage(1);
+        assert_eq!(game.active().unwrap().location.1,y - 1);
+        assert!(!game.is_lost());
+
+        //pushed into the hidden rows
+        game.add_garbage(HEIGHT);
+        assert!(game.is_lost());
+    }
+
+    #[test]
+    fn fixed_sequence_runs_out() {
+        let config = Config{ sequence: alloc::vec![PieceKind::T,PieceKind::I], preview: 1, ..Config::default() };
+        let mut game = Game::new(&config,0);
+        assert!(game.queue().iter().eq(&[PieceKind::T,PieceKind::I]));
+
+        game.spawn_next();
+        game.action(Action::Hold);
+        assert_eq!(game.active().unwrap().kind(),PieceKind::I);
+        game.action(Action::HardDrop);
+
+        //the held piece comes out last
+        game.spawn_next();
+        assert_eq!(game.active().unwrap().kind(),PieceKind::T);
+        assert!(!game.is_out_of_pieces());
+        game.action(Action::HardDrop);
+        game.spawn_next();
+        assert!(game.active().is_none());
+        assert!(game.is_out_of_pieces());
+    }
+
+    #[test]
+    fn hold_is_refused_when_the_sequence_is_empty() {
+        let config = Config{ sequence: alloc::vec![PieceKind::T], ..Config::default() };
+        let mut game = Game::new(&config,0);
+        game.spawn_next();
+        game.action(Action::SoftDrop);
+        let blocks: Vec<_> = game.active().unwrap().blocks().collect();
+
+        assert!(!game.action(Action::Hold));
+        assert_eq!(game.held(),None);
+        let tet = game.active().unwrap();
+        assert_eq!(tet.kind(),PieceKind::T);
+        assert!(tet.blocks().eq(blocks));
+    }
+
+    #[test]
+    fn pieces_spawn_centred_on_any_width() {
+        for width in [Game::MIN_WIDTH,7,Board::GAME_WIDTH,Board::MAX_WIDTH] {
+            let config = Config{ width, height: 6, hidden: 0, ..Config::default() };
+            let mut game = Game::new(&config,0);
+            assert_eq!(game.board().width(),width);
+            assert_eq!(game.board().height(),6);
+            for kind in PieceKind::ALL {
+                game.spawn(kind);
+                let tet = game.active().unwrap();
+                let left = tet.blocks().map(|b| b.0).min().unwrap();
+                let right = width as isize - 1 - tet.blocks().map(|b| b.0).max().unwrap();
+                assert!((left - right).abs() <= 1,"{:?} on {} wide",kind,width);
+            }
+        }
+    }
+
+    /// T slot two rows deep with an overhang on the left
+    fn t_slot(game: &mut Game) {
+        let bottom = HEIGHT - 1;
+        for x in 0..Board::GAME_WIDTH {
+            if x != 4 { game.board.set((x,bottom),BlockColour::Grey) }
+            if !(3..=5).contains(&x) { game.board.set((x,bottom - 1),BlockColour::Grey) }
+        }
+        game.board.set((3,bottom - 2),BlockColour::Grey);
+    }
+
+    #[test]
+    fn t_spin_double() {
+        let mut game = new_game();
+        t_slot(&mut game);
+        game.spawn(PieceKind::T);
+        assert!(game.action(Action::RotateRight));
+        game.active.as_mut().unwrap().location = (3,HEIGHT as isize - 3);
+        assert!(game.action(Action::RotateRight));
+        assert!(game.action(Action::HardDrop));
+
+        assert_eq!(game.lines(),2);
+        assert_eq!(game.score(),1200);
+        assert_eq!(game.take_events(),[Event::Clear{ lines: 2, spin: TSpin::Full, back_to_back: false }]);
+        assert!(game.take_events().is_empty());
+    }
+
+    #[test]
+    fn no_t_spin_after_moving() {
+        let mut game = new_game();
+        t_slot(&mut game);
+        game.spawn(PieceKind::T);
+        assert!(game.action(Action::RotateRight));
+        assert!(game.action(Action::RotateRight));
+        assert!(game.action(Action::MoveLeft));
+        assert!(game.action(Action::MoveRight));
+        game.active.as_mut().unwrap().location = (3,HEIGHT as isize - 3);
+        assert!(game.action(Action::HardDrop));
+
+        assert_eq!(game.lines(),2);
+        assert_eq!(game.score(),300);
+        assert_eq!(game.take_events(),[Event::Clear{ lines: 2, spin: TSpin::None, back_to_back: false }]);
+    }
+
+    #[test]
+    fn ghost_follows_active_piece() {
+        let mut game = new_game();
+        game.spawn(PieceKind::O);
+        assert_eq!(game.ghost().unwrap().location,(4,HEIGHT as isize - 2));
+
+        game.action(Action::MoveLeft);
+        game.action(Action::SoftDrop);
+        let ghost = game.ghost().unwrap();
+        assert_eq!(ghost.location,(3,HEIGHT as isize - 2));
+        assert_eq!(game.active().unwrap().location,(3,2));
+    }
+}
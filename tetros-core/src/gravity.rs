@@ -0,0 +1,106 @@
+//! how fast pieces fall at each level
+
+use core::time::Duration;
+
+/// length of one frame on the NES, gravity there is counted in frames
+const NES_FRAME: Duration = Duration::from_micros(16_639);
+
+/// guideline time per row for levels 1 to 19, `(0.8 - ((level - 1) * 0.007)) ^ (level - 1)` seconds
+/// everything above is 20G
+const GUIDELINE: [u64;19] = [
+    1_000_000, 793_000, 617_796, 472_729, 355_197,
+    262_004, 189_677, 134_735, 93_882, 64_152,
+    42_976, 28_218, 18_153, 11_439, 7_059,
+    4_264, 2_520, 1_457, 824,
+];
+
+/// NES frames per row, level 1 here is level 0 on the NES
+const NES: [u32;29] = [
+    48, 43, 38, 33, 28, 23, 18, 13, 8, 6,
+    5, 5, 5, 4, 4, 4, 3, 3, 3, 2,
+    2, 2, 2, 2, 2, 2, 2, 2, 2,
+];
+
+/// table used to look up gravity
+#[derive(Debug,Clone,Copy,PartialEq,Default)]
+pub enum GravityCurve{
+    /// guideline curve, reaching 20G at level 20
+    #[default]
+    Guideline,
+    /// NES speeds, 1 row per frame from level 30
+    Nes,
+    /// the same time per row at every level
+    Fixed(Duration),
+}
+
+/// how long a piece takes to fall a row
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Gravity{
+    Row(Duration),
+    /// pieces fall straight to the bottom, also called 20G
+    Instant,
+}
+
+impl GravityCurve{
+    pub fn gravity(self, level: u32) -> Gravity{
+        let i = level.max(1) as usize - 1;
+        match self {
+            GravityCurve::Guideline => match GUIDELINE.get(i) {
+                Some(micros) => Gravity::Row(Duration::from_micros(*micros)),
+                None => Gravity::Instant,
+            },
+            GravityCurve::Nes => Gravity::Row(NES_FRAME * *NES.get(i).unwrap_or(&1)),
+            //no time per row would never stop falling, treat it as 20G
+            GravityCurve::Fixed(row) if row.is_zero() => Gravity::Instant,
+            GravityCurve::Fixed(row) => Gravity::Row(row),
+        }
+    }
+}
+
+impl Gravity{
+    /// rows fallen after `progress` time, leaves any time left over in `progress`
+    /// returns `None` for instant gravity
+    pub fn rows(self, progress: &mut Duration) -> Option<u32>{
+        match self {
+            Gravity::Row(row) => {
+                let rows = progress.as_nanos() / row.as_nanos();
+                *progress -= row * rows as u32;
+                Some(rows as u32)
+            }
+            Gravity::Instant => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gravity_gets_faster() {
+        for curve in [GravityCurve::Guideline,GravityCurve::Nes] {
+            let mut last = Duration::MAX;
+            for level in 1..=19 {
+                let Gravity::Row(row) = curve.gravity(level) else { panic!("{:?} {} is instant",curve,level) };
+                assert!(row <= last);
+                last = row;
+            }
+        }
+        assert_eq!(GravityCurve::Guideline.gravity(20),Gravity::Instant);
+        assert_eq!(GravityCurve::Nes.gravity(40),Gravity::Row(NES_FRAME));
+    }
+
+    #[test]
+    fn partial_rows_carry_over() {
+        let gravity = Gravity::Row(Duration::from_millis(100));
+        let mut progress = Duration::from_millis(250);
+        assert_eq!(gravity.rows(&mut progress),Some(2));
+        assert_eq!(progress,Duration::from_millis(50));
+
+        progress += Duration::from_millis(40);
+        assert_eq!(gravity.rows(&mut progress),Some(0));
+        progress += Duration::from_millis(10);
+        assert_eq!(gravity.rows(&mut progress),Some(1));
+        assert_eq!(progress,Duration::ZERO);
+    }
+}
@@ -0,0 +1,142 @@
+//! high score tables
+//! stored as text, one `value name` entry per line with the best first
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// which end of a table is best
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Ranking{
+    /// scores
+    Highest,
+    /// times
+    Lowest,
+}
+
+impl Ranking{
+    /// true if `a` should be placed above `b`
+    fn beats(self, a: u64, b: u64) -> bool{
+        match self {
+            Ranking::Highest => a > b,
+            Ranking::Lowest => a < b,
+        }
+    }
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub struct Entry{
+    pub name: String,
+    pub value: u64,
+}
+
+/// the best [Leaderboard::LEN] results of a mode
+#[derive(Debug,Clone)]
+pub struct Leaderboard{
+    ranking: Ranking,
+    entries: Vec<Entry>, //best first
+}
+
+impl Leaderboard{
+    pub const LEN: usize = 10;
+    /// longest name kept, longer names are cut short
+    pub const NAME_LEN: usize = 8;
+
+    pub fn new(ranking: Ranking) -> Self{
+        Self{
+            ranking,
+            entries: Vec::new(),
+        }
+    }
+
+    /// reads a table written by [core::fmt::Display], lines that don't parse are skipped
+    pub fn parse(ranking: Ranking, text: &str) -> Self{
+        let mut board = Self::new(ranking);
+        for line in text.lines() {
+            let (value,name) = line.trim().split_once(' ').unwrap_or((line.trim(),""));
+            if let Ok(value) = value.parse() {
+                board.insert(name,value);
+            }
+        }
+        board
+    }
+
+    pub fn entries(&self) -> &[Entry]{
+        &self.entries
+    }
+
+    /// best value recorded, the personal best
+    pub fn best(&self) -> Option<u64>{
+        self.entries.first().map(|e| e.value)
+    }
+
+    /// position `value` would take, `None` if it doesn't make the table
+    /// ties go below the existing entry
+    pub fn place(&self, value: u64) -> Option<usize>{
+        let place = self.entries.iter().position(|e| self.ranking.beats(value,e.value)).unwrap_or(self.entries.len());
+        if place < Self::LEN { Some(place) } else { None }
+    }
+
+    /// adds an entry returning where it was placed
+    pub fn insert(&mut self, name: &str, value: u64) -> Option<usize>{
+        let place = self.place(value)?;
+        let name = name.chars().filter(|c| !c.is_whitespace()).take(Self::NAME_LEN).collect();
+        self.entries.insert(place,Entry{ name, value });
+        self.entries.truncate(Self::LEN);
+        Some(place)
+    }
+}
+
+impl core::fmt::Display for Leaderboard{
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result{
+        for entry in &self.entries {
+            writeln!(f,"{} {}",entry.value,entry.name)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn entries_are_kept_in_order() {
+        let mut board = Leaderboard::new(Ranking::Highest);
+        assert_eq!(board.insert("b",200),Some(0));
+        assert_eq!(board.insert("c",100),Some(1));
+        assert_eq!(board.insert("a",300),Some(0));
+        assert_eq!(board.insert("d",200),Some(2));
+        let names: Vec<_> = board.entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names,["a","b","d","c"]);
+        assert_eq!(board.best(),Some(300));
+
+        let mut times = Leaderboard::new(Ranking::Lowest);
+        times.insert("slow",90_000);
+        assert_eq!(times.insert("fast",45_000),Some(0));
+    }
+
+    #[test]
+    fn only_the_best_are_kept() {
+        let mut board = Leaderboard::new(Ranking::Highest);
+        for i in 0..20 {
+            board.insert("x",i);
+        }
+        assert_eq!(board.entries().len(),Leaderboard::LEN);
+        assert_eq!(board.place(5),None);
+        assert_eq!(board.insert("x",5),None);
+        assert_eq!(board.entries().last().unwrap().value,10);
+    }
+
+    #[test]
+    fn survives_a_round_trip() {
+        let mut board = Leaderboard::new(Ranking::Lowest);
+        board.insert("a very long name",10);
+        board.insert("",20);
+        let text = board.to_string();
+        assert_eq!(text,"10 averylon\n20 \n");
+
+        let read = Leaderboard::parse(Ranking::Lowest,&alloc::format!("{}garbage\n",text));
+        assert_eq!(read.entries(),board.entries());
+    }
+}
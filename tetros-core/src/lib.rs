@@ -0,0 +1,31 @@
+#![cfg_attr(not(test), no_std)]
+
+//! game rules for tetros without any knowledge of UEFI
+//! everything in here should be runnable on the host so it can be tested
+
+extern crate alloc;
+
+pub mod board;
+pub mod tetromino;
+pub mod srs;
+pub mod randomizer;
+pub mod game;
+pub mod scoring;
+pub mod gravity;
+pub mod lock;
+pub mod mode;
+pub mod leaderboard;
+pub mod puzzle;
+pub mod fumen;
+
+pub use board::{Board, BlockColour, ClearResult};
+pub use tetromino::{Tetromino, Rotation, PieceKind, PieceDef};
+pub use randomizer::{Randomizer, RandomizerKind};
+pub use gravity::{Gravity, GravityCurve};
+pub use lock::{LockDelay, LockReset};
+pub use game::{Game, Action, Config, Event, Stats};
+pub use scoring::TSpin;
+pub use mode::{Mode, ModeKind, Setting};
+pub use leaderboard::{Leaderboard, Ranking};
+pub use puzzle::{Puzzle, Goal, PuzzleError};
+pub use fumen::FumenError;
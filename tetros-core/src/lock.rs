@@ -0,0 +1,133 @@
+//! how long a piece may sit on the stack before it locks
+
+use core::time::Duration;
+
+/// what restarts the lock delay
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum LockReset{
+    /// moving or rotating restarts the delay, at most `limit` times per piece
+    /// once used up the piece locks as soon as it touches down
+    Move{ limit: u32 },
+    /// only falling to a new lowest row restarts the delay
+    Step,
+    /// moving or rotating always restarts the delay
+    Infinite,
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct LockDelay{
+    pub delay: Duration,
+    pub reset: LockReset,
+}
+
+impl Default for LockDelay{
+    /// guideline extended placement, 500 ms with 15 resets
+    fn default() -> Self{
+        Self{
+            delay: Duration::from_millis(500),
+            reset: LockReset::Move{ limit: 15 },
+        }
+    }
+}
+
+/// lock delay of the falling piece
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub(crate) struct LockState{
+    rules: LockDelay,
+    /// time spent touching the stack since the last reset
+    timer: Duration,
+    resets: u32,
+    /// lowest row the piece has reached, falling below it always restarts the delay
+    lowest: isize,
+}
+
+impl LockState{
+    pub(crate) fn new(rules: LockDelay, row: isize) -> Self{
+        Self{
+            rules,
+            timer: Duration::ZERO,
+            resets: 0,
+            lowest: row,
+        }
+    }
+
+    /// call after the piece falls, `row` is its new position
+    pub(crate) fn stepped(&mut self, row: isize){
+        if row > self.lowest {
+            self.lowest = row;
+            self.timer = Duration::ZERO;
+            self.resets = 0;
+        }
+    }
+
+    /// call after the piece is moved or rotated
+    pub(crate) fn moved(&mut self){
+        //only counts while the piece is touching the stack
+        if self.timer == Duration::ZERO { return }
+
+        match self.rules.reset {
+            LockReset::Move{ limit } if self.resets < limit => {
+                self.timer = Duration::ZERO;
+                self.resets += 1;
+            }
+            LockReset::Infinite => self.timer = Duration::ZERO,
+            _ => {}
+        }
+    }
+
+    /// call while the piece is touching the stack
+    /// returns true when the piece should lock
+    pub(crate) fn grounded(&mut self, elapsed: Duration) -> bool{
+        if let LockReset::Move{ limit } = self.rules.reset {
+            if self.resets >= limit { return true }
+        }
+        self.timer += elapsed;
+        self.timer >= self.rules.delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(100);
+
+    fn ticks_to_lock(state: &mut LockState, moves: bool) -> u32 {
+        for i in 1..1000 {
+            if state.grounded(TICK) { return i }
+            if moves { state.moved() }
+        }
+        panic!("never locked")
+    }
+
+    #[test]
+    fn move_reset_is_limited() {
+        let mut state = LockState::new(LockDelay::default(),0);
+        assert_eq!(ticks_to_lock(&mut state,false),5);
+
+        let mut state = LockState::new(LockDelay::default(),0);
+        assert_eq!(ticks_to_lock(&mut state,true),16);
+
+        //falling restores the resets
+        state.stepped(1);
+        assert_eq!(ticks_to_lock(&mut state,true),16);
+    }
+
+    #[test]
+    fn step_reset_ignores_moves() {
+        let rules = LockDelay{ reset: LockReset::Step, ..LockDelay::default() };
+        let mut state = LockState::new(rules,0);
+        assert_eq!(ticks_to_lock(&mut state,true),5);
+    }
+
+    #[test]
+    fn infinity_never_locks_while_moving() {
+        let rules = LockDelay{ reset: LockReset::Infinite, ..LockDelay::default() };
+        let mut state = LockState::new(rules,0);
+        for _ in 0..1000 {
+            assert!(!state.grounded(TICK));
+            state.moved();
+        }
+        assert_eq!(ticks_to_lock(&mut state,false),5);
+    }
+}
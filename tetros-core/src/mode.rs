@@ -0,0 +1,517 @@
+//! game modes
+//! a mode picks the settings a game is played with, when it is over and what gets recorded
+
+use core::time::Duration;
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use crate::game::{Game, Config};
+use crate::gravity::GravityCurve;
+use crate::leaderboard::Ranking;
+use crate::puzzle::Puzzle;
+
+/// available modes, so menus can list them
+/// modes with a [Setting] carry its value, each value keeps its own high scores
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum ModeKind{
+    /// clear [Marathon::GOAL] lines starting from `level`
+    Marathon{ level: u32 },
+    /// only ends when the player tops out
+    Endless{ level: u32 },
+    /// clear `lines` as fast as possible
+    Sprint{ lines: u32 },
+    /// score as much as possible in `minutes`
+    Ultra{ minutes: u32 },
+    /// clear `rows` of garbage as fast as possible, more keeps coming if `rising`
+    Dig{ rows: u32, rising: bool },
+    /// a [Puzzle] read with [Puzzle::parse], not in [Self::ALL] as [Self::build] can only give an empty one
+    Puzzle,
+}
+
+/// a number picked before a game starts, like the line goal of a sprint
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct Setting{
+    pub name: &'static str,
+    pub value: u32,
+    pub min: u32,
+    pub max: u32,
+    pub step: u32,
+}
+
+impl ModeKind{
+    pub const ALL: [ModeKind;6] = [
+        ModeKind::Marathon{ level: 1 },
+        ModeKind::Endless{ level: 1 },
+        ModeKind::Sprint{ lines: 40 },
+        ModeKind::Ultra{ minutes: 2 },
+        ModeKind::Dig{ rows: 10, rising: false },
+        ModeKind::Dig{ rows: 10, rising: true },
+    ];
+
+    pub fn name(self) -> &'static str{
+        match self {
+            ModeKind::Marathon{..} => "Marathon",
+            ModeKind::Endless{..} => "Endless",
+            ModeKind::Sprint{..} => "Sprint",
+            ModeKind::Ultra{..} => "Ultra",
+            ModeKind::Dig{ rising: false, .. } => "Dig",
+            ModeKind::Dig{ rising: true, .. } => "Dig Rising",
+            ModeKind::Puzzle => "Puzzle",
+        }
+    }
+
+    /// name and setting without spaces, for file names
+    pub fn id(self) -> String{
+        let mut id = self.name().to_lowercase().replace(' ',"");
+        if let Some(setting) = self.setting() {
+            id.push_str(&setting.value.to_string());
+        }
+        id
+    }
+
+    /// the value the player can change before starting, if any
+    pub fn setting(self) -> Option<Setting>{
+        match self {
+            ModeKind::Marathon{ level } => Some(Setting{ name: "Level", value: level, min: 1, max: 15, step: 1 }),
+            ModeKind::Endless{ level } => Some(Setting{ name: "Level", value: level, min: 1, max: 20, step: 1 }),
+            ModeKind::Sprint{ lines } => Some(Setting{ name: "Lines", value: lines, min: 10, max: 200, step: 10 }),
+            ModeKind::Ultra{ minutes } => Some(Setting{ name: "Minutes", value: minutes, min: 1, max: 10, step: 1 }),
+            ModeKind::Dig{ rows, .. } => Some(Setting{ name: "Rows", value: rows, min: 2, max: 12, step: 1 }),
+            ModeKind::Puzzle => None,
+        }
+    }
+
+    /// returns self with its setting changed to `value`, clamped to the setting's range
+    pub fn with_setting(self, value: u32) -> Self{
+        let value = match self.setting() {
+            Some(setting) => value.clamp(setting.min,setting.max),
+            None => return self,
+        };
+        match self {
+            ModeKind::Marathon{..} => ModeKind::Marathon{ level: value },
+            ModeKind::Endless{..} => ModeKind::Endless{ level: value },
+            ModeKind::Sprint{..} => ModeKind::Sprint{ lines: value },
+            ModeKind::Ultra{..} => ModeKind::Ultra{ minutes: value },
+            ModeKind::Dig{ rising, .. } => ModeKind::Dig{ rows: value, rising },
+            ModeKind::Puzzle => self,
+        }
+    }
+
+    pub fn build(self) -> Box<dyn Mode>{
+        match self {
+            ModeKind::Marathon{ level } => Box::new(Marathon{ level, goal: Some(Marathon::GOAL) }),
+            ModeKind::Endless{ level } => Box::new(Marathon{ level, goal: None }),
+            ModeKind::Sprint{ lines } => Box::new(Sprint{ lines: lines as usize }),
+            ModeKind::Ultra{ minutes } => Box::new(Ultra{ time: Duration::from_secs(minutes as u64 * 60) }),
+            ModeKind::Dig{ rows, rising } => Box::new(Dig{ rows: rows as usize, rise: rising.then_some(Dig::RISE) }),
+            ModeKind::Puzzle => Box::new(Puzzle::default()),
+        }
+    }
+}
+
+/// rules a mode adds on top of [Game]
+pub trait Mode{
+    fn kind(&self) -> ModeKind;
+
+    /// settings for a new game
+    fn config(&self) -> Config{
+        Config::default()
+    }
+
+    /// a game ready to play, for modes that need more than [Self::config] to set up
+    fn new_game(&self, seed: u64) -> Game{
+        Game::new(&self.config(),seed)
+    }
+
+    /// true once the goal has been reached, checked every frame
+    fn finished(&self, _game: &Game) -> bool{
+        false
+    }
+
+    /// heading for the results screen
+    fn title(&self, game: &Game) -> &'static str{
+        match (game.is_lost(),game.is_timed_out()) {
+            (true,_) => "Game over",
+            (false,true) => "Time up",
+            (false,false) => "Complete",
+        }
+    }
+
+    /// how [Self::record] values are compared
+    fn ranking(&self) -> Ranking{
+        Ranking::Highest
+    }
+
+    /// value for the high score table, `None` if the game doesn't count
+    fn record(&self, game: &Game) -> Option<u64>{
+        Some(game.score())
+    }
+
+    /// shows a value from [Self::record]
+    fn format_record(&self, value: u64) -> String{
+        value.to_string()
+    }
+
+    /// extra lines for the HUD while playing
+    fn hud(&self, _game: &Game) -> Vec<String>{
+        Vec::new()
+    }
+
+    /// lines shown on the results screen
+    fn results(&self, game: &Game) -> Vec<String>{
+        let mut lines = Vec::new();
+        lines.push(alloc::format!("Score {}",game.score()));
+        lines.push(alloc::format!("Lines {}",game.lines()));
+        lines.push(alloc::format!("Level {}",game.level()));
+        lines
+    }
+}
+
+/// `m:ss.mmm`
+pub fn format_time(time: Duration) -> String{
+    let millis = time.as_millis();
+    alloc::format!("{}:{:02}.{:03}",millis / 60_000,(millis / 1000) % 60,millis % 1000)
+}
+
+/// pieces per second
+fn pps(game: &Game) -> f32{
+    match game.time().as_secs_f32() {
+        secs if secs > 0.0 => game.pieces() as f32 / secs,
+        _ => 0.0,
+    }
+}
+
+/// line clears, T-spins and combos over the game
+fn stats(game: &Game) -> Vec<String>{
+    let stats = game.stats();
+    let mut lines = Vec::new();
+    for (name,count) in ["Singles","Doubles","Triples","Tetrises"].iter().zip(stats.clears) {
+        lines.push(alloc::format!("{} {}",name,count));
+    }
+    lines.push(alloc::format!("T-Spins {}",stats.t_spins));
+    lines.push(alloc::format!("Back-to-Backs {}",stats.back_to_backs));
+    lines.push(alloc::format!("Max combo {}",stats.max_combo));
+    lines
+}
+
+/// levels up every 10 lines from `level`, finished after `goal` lines if there is one
+pub struct Marathon{
+    pub level: u32,
+    pub goal: Option<usize>,
+}
+
+impl Marathon{
+    /// lines in a game with a goal, the end of level 15 when starting from 1
+    pub const GOAL: usize = 150;
+}
+
+impl Mode for Marathon{
+    fn kind(&self) -> ModeKind{
+        match self.goal {
+            Some(_) => ModeKind::Marathon{ level: self.level },
+            None => ModeKind::Endless{ level: self.level },
+        }
+    }
+
+    fn config(&self) -> Config{
+        Config{
+            start_level: self.level,
+            ..Config::default()
+        }
+    }
+
+    fn finished(&self, game: &Game) -> bool{
+        self.goal.is_some_and(|goal| game.lines() >= goal)
+    }
+
+    fn hud(&self, game: &Game) -> Vec<String>{
+        let mut lines = Vec::new();
+        lines.push(format_time(game.time()));
+        if let Some(goal) = self.goal {
+            lines.push(alloc::format!("{} to go",goal.saturating_sub(game.lines())));
+        }
+        lines
+    }
+
+    fn results(&self, game: &Game) -> Vec<String>{
+        let mut lines = Vec::new();
+        lines.push(alloc::format!("Score {}",game.score()));
+        lines.push(alloc::format!("Level {}",game.level()));
+        match self.goal {
+            Some(goal) => lines.push(alloc::format!("Lines {}/{}",game.lines().min(goal),goal)),
+            None => lines.push(alloc::format!("Lines {}",game.lines())),
+        }
+        lines.push(alloc::format!("Time {}",format_time(game.time())));
+        lines.push(alloc::format!("PPS {:.2}",pps(game)));
+        lines.push(String::new());
+        lines.extend(stats(game));
+        lines
+    }
+}
+
+/// race to clear `lines`, times are recorded in milliseconds
+pub struct Sprint{
+    pub lines: usize,
+}
+
+impl Mode for Sprint{
+    fn kind(&self) -> ModeKind{
+        ModeKind::Sprint{ lines: self.lines as u32 }
+    }
+
+    fn finished(&self, game: &Game) -> bool{
+        game.lines() >= self.lines
+    }
+
+    fn ranking(&self) -> Ranking{
+        Ranking::Lowest
+    }
+
+    fn record(&self, game: &Game) -> Option<u64>{
+        match self.finished(game) {
+            true => Some(game.time().as_millis() as u64),
+            false => None,
+        }
+    }
+
+    fn format_record(&self, value: u64) -> String{
+        format_time(Duration::from_millis(value))
+    }
+
+    fn hud(&self, game: &Game) -> Vec<String>{
+        let mut lines = Vec::new();
+        lines.push(format_time(game.time()));
+        lines.push(alloc::format!("{} to go",self.lines.saturating_sub(game.lines())));
+        lines
+    }
+
+    fn results(&self, game: &Game) -> Vec<String>{
+        let mut lines = Vec::new();
+        lines.push(alloc::format!("Time {}",format_time(game.time())));
+        lines.push(alloc::format!("Lines {}/{}",game.lines().min(self.lines),self.lines));
+        lines.push(alloc::format!("Pieces {}",game.pieces()));
+        lines.push(alloc::format!("PPS {:.2}",pps(game)));
+        lines.push(alloc::format!("Keys {}",game.inputs()));
+        lines
+    }
+}
+
+/// score attack against the clock, the game stops at `time` even mid piece
+pub struct Ultra{
+    pub time: Duration,
+}
+
+impl Ultra{
+    /// gravity stays at the level 1 speed however many lines are cleared
+    const GRAVITY: Duration = Duration::from_secs(1);
+}
+
+impl Mode for Ultra{
+    fn kind(&self) -> ModeKind{
+        ModeKind::Ultra{ minutes: (self.time.as_secs() / 60) as u32 }
+    }
+
+    fn config(&self) -> Config{
+        Config{
+            gravity: GravityCurve::Fixed(Self::GRAVITY),
+            time_limit: Some(self.time),
+            ..Config::default()
+        }
+    }
+
+    fn finished(&self, game: &Game) -> bool{
+        game.is_timed_out()
+    }
+
+    /// only games that ran out the clock count
+    fn record(&self, game: &Game) -> Option<u64>{
+        match self.finished(game) {
+            true => Some(game.score()),
+            false => None,
+        }
+    }
+
+    fn hud(&self, game: &Game) -> Vec<String>{
+        alloc::vec![format_time(game.time_left().unwrap_or_default())]
+    }
+
+    fn results(&self, game: &Game) -> Vec<String>{
+        let mut lines = Vec::new();
+        lines.push(alloc::format!("Score {}",game.score()));
+        lines.push(alloc::format!("Lines {}",game.lines()));
+        lines.push(alloc::format!("Pieces {}",game.pieces()));
+        lines.push(alloc::format!("PPS {:.2}",pps(game)));
+        lines.push(alloc::format!("Keys {}",game.inputs()));
+        lines
+    }
+}
+
+/// race to clear `rows` of garbage the board starts with, times are recorded in milliseconds
+/// with `rise` set a new garbage row comes up from the bottom that often
+pub struct Dig{
+    pub rows: usize,
+    pub rise: Option<Duration>,
+}
+
+impl Dig{
+    pub const RISE: Duration = Duration::from_secs(5);
+}
+
+impl Mode for Dig{
+    fn kind(&self) -> ModeKind{
+        ModeKind::Dig{ rows: self.rows as u32, rising: self.rise.is_some() }
+    }
+
+    fn config(&self) -> Config{
+        Config{
+            garbage: self.rows,
+            garbage_rise: self.rise,
+            ..Config::default()
+        }
+    }
+
+    fn finished(&self, game: &Game) -> bool{
+        game.garbage_cleared() >= self.rows
+    }
+
+    fn ranking(&self) -> Ranking{
+        Ranking::Lowest
+    }
+
+    fn record(&self, game: &Game) -> Option<u64>{
+        match self.finished(game) {
+            true => Some(game.time().as_millis() as u64),
+            false => None,
+        }
+    }
+
+    fn format_record(&self, value: u64) -> String{
+        format_time(Duration::from_millis(value))
+    }
+
+    fn hud(&self, game: &Game) -> Vec<String>{
+        let mut lines = Vec::new();
+        lines.push(format_time(game.time()));
+        lines.push(alloc::format!("{} to go",self.rows.saturating_sub(game.garbage_cleared())));
+        lines
+    }
+
+    fn results(&self, game: &Game) -> Vec<String>{
+        let mut lines = Vec::new();
+        lines.push(alloc::format!("Time {}",format_time(game.time())));
+        lines.push(alloc::format!("Garbage {}/{}",game.garbage_cleared().min(self.rows),self.rows));
+        lines.push(alloc::format!("Lines {}",game.lines()));
+        lines.push(alloc::format!("Pieces {}",game.pieces()));
+        lines.push(alloc::format!("PPS {:.2}",pps(game)));
+        lines.push(alloc::format!("Keys {}",game.inputs()));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Board, BlockColour};
+    use crate::game::Action;
+    use crate::tetromino::PieceKind;
+
+    #[test]
+    fn every_mode_builds_itself() {
+        for kind in ModeKind::ALL {
+            let mode = kind.build();
+            assert_eq!(mode.kind(),kind);
+            let game = Game::new(&mode.config(),0);
+            assert!(!mode.finished(&game));
+        }
+    }
+
+    #[test]
+    fn settings_are_clamped() {
+        let sprint = ModeKind::Sprint{ lines: 40 };
+        assert_eq!(sprint.with_setting(20),ModeKind::Sprint{ lines: 20 });
+        assert_eq!(sprint.with_setting(0),ModeKind::Sprint{ lines: 10 });
+        assert_eq!(sprint.id(),"sprint40");
+        assert_eq!(ModeKind::Dig{ rows: 10, rising: true }.id(),"digrising10");
+        assert_eq!(ModeKind::Marathon{ level: 1 }.with_setting(20),ModeKind::Marathon{ level: 15 });
+        assert_eq!(ModeKind::Endless{ level: 1 }.with_setting(20),ModeKind::Endless{ level: 20 });
+    }
+
+    #[test]
+    fn marathon_starts_at_the_chosen_level() {
+        let mode = ModeKind::Marathon{ level: 5 }.build();
+        let game = Game::new(&mode.config(),0);
+        assert_eq!(game.level(),5);
+        assert_eq!(mode.hud(&game)[1],"150 to go");
+        assert!(!ModeKind::Endless{ level: 5 }.build().finished(&game));
+    }
+
+    #[test]
+    fn dig_is_finished_once_the_garbage_is_cleared() {
+        let mode = Dig{ rows: 1, rise: None };
+        let mut game = Game::new(&mode.config(),0);
+        let bottom = Board::GAME_HEIGHT + Board::HIDDEN_ROWS - 1;
+        let hole = game.board().row(bottom).trailing_ones() as isize;
+        assert!(!mode.finished(&game));
+
+        game.spawn(PieceKind::I);
+        game.action(Action::RotateRight);
+        let tet = game.active().unwrap();
+        let column = tet.blocks().next().unwrap().0;
+        for _ in 0..(hole - column).abs() {
+            game.action(if hole > column { Action::MoveRight } else { Action::MoveLeft });
+        }
+        game.action(Action::HardDrop);
+        assert_eq!(game.garbage_cleared(),1);
+        assert!(mode.finished(&game));
+        assert_eq!(mode.record(&game),Some(0));
+    }
+
+    #[test]
+    fn times_are_formatted() {
+        assert_eq!(format_time(Duration::from_millis(83_456)),"1:23.456");
+        assert_eq!(format_time(Duration::ZERO),"0:00.000");
+    }
+
+    #[test]
+    fn sprint_records_the_time_once_finished() {
+        let mode = Sprint{ lines: 4 };
+        let mut game = Game::new(&mode.config(),0);
+        let bottom = Board::GAME_HEIGHT + Board::HIDDEN_ROWS - 1;
+        for y in bottom - 3..=bottom {
+            for x in 0..Board::GAME_WIDTH - 1 {
+                game.board_mut().set((x,y),BlockColour::Grey);
+            }
+        }
+        game.spawn(PieceKind::I);
+        game.advance(Duration::from_millis(1500));
+        assert_eq!(mode.record(&game),None);
+
+        game.action(Action::RotateRight);
+        for _ in 0..Board::GAME_WIDTH {
+            game.action(Action::MoveRight);
+        }
+        game.action(Action::HardDrop);
+        assert!(mode.finished(&game));
+        assert_eq!(mode.record(&game),Some(1500));
+        assert_eq!(game.pieces(),1);
+        assert_eq!(game.inputs(),2 + Board::GAME_WIDTH);
+    }
+
+    #[test]
+    fn ultra_ends_on_time_with_fixed_gravity() {
+        let mode = Ultra{ time: Duration::from_secs(120) };
+        let config = mode.config();
+        assert_eq!(config.gravity.gravity(1),config.gravity.gravity(15));
+
+        let mut game = Game::new(&config,0);
+        game.spawn_next();
+        game.advance(Duration::from_secs(119));
+        assert!(!mode.finished(&game));
+        assert_eq!(mode.record(&game),None);
+        assert_eq!(mode.hud(&game),["0:01.000"]);
+
+        game.advance(Duration::from_secs(2));
+        assert!(mode.finished(&game));
+        assert_eq!(mode.record(&game),Some(game.score()));
+    }
+}
@@ -0,0 +1,348 @@
+//! puzzles, a preset board played with a fixed set of pieces towards a goal
+//! written as text so they can be kept in files, lines before the board are `key value`:
+//!
+//! ```text
+//! # comments start with a hash
+//! name First tetris
+//! goal lines 4
+//! pieces I
+//! board
+//! XXXXXXXXX.
+//! XXXXXXXXX.
+//! XXXXXXXXX.
+//! XXXXXXXXX.
+//! ```
+//!
+//! `goal` is `lines n`, `perfect` or `practice`, `pieces` are letters dealt left to right.
+//! board rows sit on the floor, `.` is empty, piece letters are blocks of that
+//! piece's colour and `X` or `#` is garbage
+//!
+//! instead of a board a page of a fumen can be given with `fumen v115@...` and `page n`,
+//! without a `pieces` line the pieces of its quiz comment or the piece on the page are used.
+//! practice has no goal, without any pieces given they are dealt at random
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use crate::board::{Board, BlockColour};
+use crate::fumen::{self, FumenError, Page};
+use crate::game::{Game, Config};
+use crate::mode::{Mode, ModeKind};
+use crate::tetromino::PieceKind;
+
+/// what has to be done before the pieces run out
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Goal{
+    Lines(usize),
+    /// clear lines leaving the board empty
+    PerfectClear,
+    /// play on from the board until topping out
+    Practice,
+}
+
+impl core::fmt::Display for Goal{
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result{
+        match self {
+            Goal::Lines(1) => write!(f,"Clear 1 line"),
+            Goal::Lines(lines) => write!(f,"Clear {} lines",lines),
+            Goal::PerfectClear => write!(f,"Perfect clear"),
+            Goal::Practice => write!(f,"Practice"),
+        }
+    }
+}
+
+/// why [Puzzle::parse] failed, `line` counts from 1
+#[derive(Debug,Clone,PartialEq)]
+pub enum PuzzleError{
+    NoBoard,
+    NoPieces,
+    NoGoal,
+    /// a line before the board that isn't understood
+    BadLine(usize),
+    /// a character that isn't a piece or a block
+    BadChar{ line: usize, c: char },
+    /// more rows or columns than [Board::GAME_WIDTH] by the game height
+    TooBig,
+    Fumen(FumenError),
+    /// the fumen has fewer pages than `page`
+    NoPage(usize),
+}
+
+impl core::fmt::Display for PuzzleError{
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result{
+        match self {
+            PuzzleError::NoBoard => write!(f,"no board"),
+            PuzzleError::NoPieces => write!(f,"no pieces"),
+            PuzzleError::NoGoal => write!(f,"no goal"),
+            PuzzleError::BadLine(line) => write!(f,"line {} not understood",line),
+            PuzzleError::BadChar{ line, c } => write!(f,"unknown '{}' on line {}",c,line),
+            PuzzleError::TooBig => write!(f,"board too big"),
+            PuzzleError::Fumen(e) => write!(f,"{}",e),
+            PuzzleError::NoPage(page) => write!(f,"no page {} in fumen",page),
+        }
+    }
+}
+
+/// a puzzle played as a [Mode]
+#[derive(Debug,Clone,PartialEq)]
+pub struct Puzzle{
+    pub name: String,
+    pub goal: Goal,
+    pub pieces: Vec<PieceKind>,
+    /// top row first, the last row is the floor
+    pub rows: Vec<Vec<BlockColour>>,
+}
+
+impl Default for Puzzle{
+    /// nothing to place and nothing to clear
+    fn default() -> Self{
+        Self{
+            name: String::new(),
+            goal: Goal::Lines(0),
+            pieces: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+}
+
+impl Puzzle{
+    /// reads a puzzle in the format described in [self], `name` is used if the text has none
+    pub fn parse(name: &str, text: &str) -> Result<Self, PuzzleError>{
+        let mut puzzle = Self{ name: name.to_string(), ..Self::default() };
+        let mut goal = None;
+        let mut code = None;
+        let mut page: usize = 1;
+        let mut lines = text.lines().enumerate();
+
+        //settings up to the board
+        let mut board = false;
+        for (i,line) in lines.by_ref() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue }
+
+            let (key,value) = line.split_once(' ').unwrap_or((line,""));
+            let value = value.trim();
+            match key {
+                "name" => puzzle.name = value.to_string(),
+                "goal" => goal = match value.split_once(' ').unwrap_or((value,"")) {
+                    ("lines",n) => Some(Goal::Lines(n.trim().parse().map_err(|_| PuzzleError::BadLine(i + 1))?)),
+                    ("perfect",_) => Some(Goal::PerfectClear),
+                    ("practice",_) => Some(Goal::Practice),
+                    _ => return Err(PuzzleError::BadLine(i + 1)),
+                },
+                "pieces" => for c in value.chars().filter(|c| !c.is_whitespace()) {
+                    let kind = PieceKind::from_letter(c).ok_or(PuzzleError::BadChar{ line: i + 1, c })?;
+                    puzzle.pieces.push(kind);
+                },
+                "fumen" => code = Some(value),
+                "page" => page = value.parse().map_err(|_| PuzzleError::BadLine(i + 1))?,
+                "board" => {
+                    board = true;
+                    break
+                }
+                _ => return Err(PuzzleError::BadLine(i + 1)),
+            }
+        }
+
+        for (i,line) in lines {
+            let line = line.trim_end();
+            if line.is_empty() { continue }
+
+            let mut row = Vec::new();
+            for c in line.chars() {
+                row.push(match c {
+                    '.' | ' ' => BlockColour::None,
+                    'X' | 'x' | '#' => BlockColour::Grey,
+                    c => PieceKind::from_letter(c).ok_or(PuzzleError::BadChar{ line: i + 1, c })?.def().colour,
+                });
+            }
+            if row.len() > Board::GAME_WIDTH { return Err(PuzzleError::TooBig) }
+            row.resize(Board::GAME_WIDTH,BlockColour::None);
+            puzzle.rows.push(row);
+        }
+        if let Some(code) = code.filter(|_| !board) {
+            let pages = fumen::decode(code).map_err(PuzzleError::Fumen)?;
+            let page = page.checked_sub(1).and_then(|i| pages.get(i)).ok_or(PuzzleError::NoPage(page))?;
+            puzzle.set_page(page);
+        } else if !board {
+            return Err(PuzzleError::NoBoard)
+        }
+        if puzzle.rows.len() > Board::GAME_HEIGHT { return Err(PuzzleError::TooBig) }
+
+        puzzle.goal = goal.ok_or(PuzzleError::NoGoal)?;
+        if puzzle.pieces.is_empty() && (puzzle.goal != Goal::Practice) { return Err(PuzzleError::NoPieces) }
+        Ok(puzzle)
+    }
+
+    /// takes the board from a fumen page, and its pieces unless some were already given
+    fn set_page(&mut self, page: &Page){
+        let empty = page.rows.iter().take_while(|row| row.iter().all(|c| *c == BlockColour::None)).count();
+        self.rows = page.rows[empty..].to_vec();
+        if self.pieces.is_empty() {
+            self.pieces = page.quiz().unwrap_or_else(|| page.piece.map(|p| p.kind).into_iter().collect());
+        }
+    }
+
+    /// true once the goal has been met
+    pub fn solved(&self, game: &Game) -> bool{
+        match self.goal {
+            Goal::Lines(lines) => game.lines() >= lines,
+            Goal::PerfectClear => (game.lines() > 0) && game.board().highest_block().is_none(),
+            Goal::Practice => false,
+        }
+    }
+}
+
+impl Mode for Puzzle{
+    fn kind(&self) -> ModeKind{
+        ModeKind::Puzzle
+    }
+
+    fn config(&self) -> Config{
+        Config{
+            sequence: self.pieces.clone(),
+            ..Config::default()
+        }
+    }
+
+    /// places the board, the rows are put on the floor
+    fn new_game(&self, seed: u64) -> Game{
+        let mut game = Game::new(&self.config(),seed);
+        let board = game.board_mut();
+        let top = board.height() - self.rows.len();
+        for (y,row) in self.rows.iter().enumerate() {
+            for (x,colour) in row.iter().enumerate() {
+                board.set((x,top + y),*colour);
+            }
+        }
+        game
+    }
+
+    fn finished(&self, game: &Game) -> bool{
+        self.solved(game) || game.is_out_of_pieces()
+    }
+
+    fn title(&self, game: &Game) -> &'static str{
+        match (self.goal,self.solved(game)) {
+            (Goal::Practice,_) => "Game over",
+            (_,true) => "Solved",
+            (_,false) => "Failed",
+        }
+    }
+
+    /// puzzles only pass or fail
+    fn record(&self, _game: &Game) -> Option<u64>{
+        None
+    }
+
+    fn hud(&self, game: &Game) -> Vec<String>{
+        let mut lines = Vec::new();
+        lines.push(alloc::format!("{}",self.goal));
+        if game.is_fixed() {
+            let left = game.queue().len() + game.active().is_some() as usize + game.held().is_some() as usize;
+            lines.push(alloc::format!("{} pieces left",left));
+        }
+        lines
+    }
+
+    fn results(&self, game: &Game) -> Vec<String>{
+        let mut lines = Vec::new();
+        lines.push(self.name.clone());
+        lines.push(alloc::format!("{}",self.goal));
+        lines.push(alloc::format!("Lines {}",game.lines()));
+        match self.pieces.len() {
+            0 => lines.push(alloc::format!("Pieces {}",game.pieces())),
+            len => lines.push(alloc::format!("Pieces {}/{}",game.pieces(),len)),
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Action;
+
+    const TETRIS: &str = "# four rows with a well on the right
+name Tetris
+goal lines 4
+pieces I O
+
+board
+O.........
+XXXXXXXXX.
+XXXXXXXXX.
+XXXXXXXXX.
+XXXXXXXXX.
+";
+
+    #[test]
+    fn puzzles_are_parsed() {
+        let puzzle = Puzzle::parse("file",TETRIS).unwrap();
+        assert_eq!(puzzle.name,"Tetris");
+        assert_eq!(puzzle.goal,Goal::Lines(4));
+        assert_eq!(puzzle.pieces,[PieceKind::I,PieceKind::O]);
+        assert_eq!(puzzle.rows.len(),5);
+        assert_eq!(puzzle.rows[0][0],BlockColour::Yellow);
+        assert_eq!(puzzle.rows[4][0],BlockColour::Grey);
+
+        assert_eq!(Puzzle::parse("file","goal perfect\npieces I\n").unwrap_err(),PuzzleError::NoBoard);
+        assert_eq!(Puzzle::parse("file","goal perfect\npieces I\nboard\nXXQ\n").unwrap_err(),PuzzleError::BadChar{ line: 4, c: 'Q' });
+        assert_eq!(Puzzle::parse("file","goal sideways\n").unwrap_err(),PuzzleError::BadLine(1));
+        assert_eq!(Puzzle::parse("file","pieces I\nboard\n").unwrap_err(),PuzzleError::NoGoal);
+        assert_eq!(Puzzle::parse("file","goal practice\nboard\nX\n").unwrap().pieces,[]);
+    }
+
+    #[test]
+    fn boards_come_from_fumen_pages() {
+        let mut board = Board::new(Board::GAME_WIDTH,Board::GAME_HEIGHT,Board::HIDDEN_ROWS);
+        let bottom = board.height() - 1;
+        board.set((0,bottom),BlockColour::Grey);
+        board.set((1,bottom - 1),BlockColour::Red);
+        let mut t = crate::Tetromino::from_kind(PieceKind::T);
+        t.location = (4,0);
+        let code = fumen::encode(&board,Some(&t)).unwrap();
+
+        let puzzle = Puzzle::parse("file",&alloc::format!("goal lines 1\nfumen {}\n",code)).unwrap();
+        assert_eq!(puzzle.pieces,[PieceKind::T]);
+        assert_eq!(puzzle.rows.len(),2);
+        assert_eq!(puzzle.rows[0][1],BlockColour::Red);
+        assert_eq!(puzzle.rows[1][0],BlockColour::Grey);
+
+        let text = alloc::format!("goal perfect\npieces OI\npage 2\nfumen {}\n",code);
+        assert_eq!(Puzzle::parse("file",&text).unwrap_err(),PuzzleError::NoPage(2));
+    }
+
+    #[test]
+    fn solving_a_puzzle() {
+        let puzzle = Puzzle::parse("file",TETRIS).unwrap();
+        let mut game = puzzle.new_game(0);
+        let bottom = game.board().height() - 1;
+        assert_eq!(game.board().get((0,bottom)),BlockColour::Grey);
+        assert_eq!(game.board().get((0,bottom - 4)),BlockColour::Yellow);
+
+        //the O is dropped without clearing anything
+        game.spawn_next();
+        game.action(Action::Hold);
+        game.action(Action::HardDrop);
+        assert!(!puzzle.finished(&game));
+
+        //then the I from hold is dropped flat
+        game.spawn_next();
+        assert_eq!(game.active().unwrap().kind(),PieceKind::I);
+        game.action(Action::HardDrop);
+        game.spawn_next();
+        assert!(game.is_out_of_pieces());
+        assert!(puzzle.finished(&game));
+        assert_eq!(puzzle.title(&game),"Failed");
+
+        let mut game = puzzle.new_game(0);
+        game.spawn_next();
+        game.action(Action::RotateRight);
+        for _ in 0..Board::GAME_WIDTH {
+            game.action(Action::MoveRight);
+        }
+        game.action(Action::HardDrop);
+        assert!(puzzle.solved(&game));
+        assert_eq!(puzzle.title(&game),"Solved");
+    }
+}
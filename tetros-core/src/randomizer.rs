@@ -0,0 +1,206 @@
+//! piece randomizers
+//! every randomizer is seeded so the same seed always produces the same pieces
+
+use alloc::boxed::Box;
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use crate::tetromino::PieceKind;
+
+/// decides the order pieces are dealt in
+pub trait Randomizer{
+    /// returns the next piece to spawn
+    fn next_piece(&mut self) -> PieceKind;
+}
+
+/// available randomizers, so game modes can choose one
+#[derive(Debug,Clone,Copy,PartialEq,Default)]
+pub enum RandomizerKind{
+    /// 7-bag, the default
+    #[default]
+    Bag,
+    /// every piece is picked independently
+    Random,
+    /// TGM style, avoids the last 4 pieces with up to `rolls` rerolls
+    Tgm{ rolls: u8 },
+    /// NES style, rerolls once if the piece repeats
+    Nes,
+}
+
+impl RandomizerKind{
+    pub fn build(self, seed: u64) -> Box<dyn Randomizer>{
+        match self {
+            RandomizerKind::Bag => Box::new(Bag::new(seed)),
+            RandomizerKind::Random => Box::new(PureRandom::new(seed)),
+            RandomizerKind::Tgm{ rolls } => Box::new(Tgm::new(seed, rolls)),
+            RandomizerKind::Nes => Box::new(Nes::new(seed)),
+        }
+    }
+}
+
+/// deals all seven pieces in a random order before starting again
+pub struct Bag{
+    rng: SmallRng,
+    bag: [PieceKind;7],
+    pos: usize,
+}
+
+impl Bag{
+    pub fn new(seed: u64) -> Self{
+        Self{
+            rng: SmallRng::seed_from_u64(seed),
+            bag: PieceKind::ALL,
+            pos: PieceKind::ALL.len(), //empty, filled on first call
+        }
+    }
+}
+
+impl Randomizer for Bag{
+    fn next_piece(&mut self) -> PieceKind{
+        if self.pos >= self.bag.len() {
+            self.bag.shuffle(&mut self.rng);
+            self.pos = 0;
+        }
+        let piece = self.bag[self.pos];
+        self.pos += 1;
+        piece
+    }
+}
+
+pub struct PureRandom{
+    rng: SmallRng,
+}
+
+impl PureRandom{
+    pub fn new(seed: u64) -> Self{
+        Self{
+            rng: SmallRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Randomizer for PureRandom{
+    fn next_piece(&mut self) -> PieceKind{
+        PieceKind::ALL[self.rng.gen_range(0..PieceKind::ALL.len())]
+    }
+}
+
+/// TGM2 randomizer
+/// rolls up to `rolls` times for a piece not in the last 4 dealt,
+/// the history starts as Z S S Z and the first piece is never S, Z or O
+pub struct Tgm{
+    rng: SmallRng,
+    rolls: u8,
+    history: [PieceKind;4],
+    first: bool,
+}
+
+impl Tgm{
+    pub fn new(seed: u64, rolls: u8) -> Self{
+        Self{
+            rng: SmallRng::seed_from_u64(seed),
+            rolls,
+            history: [PieceKind::Z, PieceKind::S, PieceKind::S, PieceKind::Z],
+            first: true,
+        }
+    }
+
+    fn roll(&mut self) -> PieceKind{
+        PieceKind::ALL[self.rng.gen_range(0..PieceKind::ALL.len())]
+    }
+}
+
+impl Randomizer for Tgm{
+    fn next_piece(&mut self) -> PieceKind{
+        let piece = if self.first {
+            self.first = false;
+            const FIRST: [PieceKind;4] = [PieceKind::I, PieceKind::J, PieceKind::L, PieceKind::T];
+            FIRST[self.rng.gen_range(0..FIRST.len())]
+        } else {
+            let mut piece = self.roll();
+            for _ in 1..self.rolls {
+                if !self.history.contains(&piece) { break }
+                piece = self.roll();
+            }
+            piece
+        };
+
+        self.history.rotate_right(1);
+        self.history[0] = piece;
+        piece
+    }
+}
+
+/// NES randomizer
+/// rolls 8 sides where the 8th side or a repeat of the last piece causes one reroll
+pub struct Nes{
+    rng: SmallRng,
+    last: Option<PieceKind>,
+}
+
+impl Nes{
+    pub fn new(seed: u64) -> Self{
+        Self{
+            rng: SmallRng::seed_from_u64(seed),
+            last: None,
+        }
+    }
+}
+
+impl Randomizer for Nes{
+    fn next_piece(&mut self) -> PieceKind{
+        let roll = self.rng.gen_range(0..PieceKind::ALL.len() + 1);
+        let piece = match PieceKind::ALL.get(roll) {
+            Some(p) if Some(*p) != self.last => *p,
+            _ => PieceKind::ALL[self.rng.gen_range(0..PieceKind::ALL.len())],
+        };
+        self.last = Some(piece);
+        piece
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    const KINDS: [RandomizerKind;4] = [
+        RandomizerKind::Bag,
+        RandomizerKind::Random,
+        RandomizerKind::Tgm{ rolls: 6 },
+        RandomizerKind::Nes,
+    ];
+
+    fn deal(r: &mut dyn Randomizer, n: usize) -> Vec<PieceKind>{
+        (0..n).map(|_| r.next_piece()).collect()
+    }
+
+    #[test]
+    fn same_seed_same_pieces() {
+        for kind in KINDS {
+            let a = deal(&mut *kind.build(0xdead_beef),100);
+            let b = deal(&mut *kind.build(0xdead_beef),100);
+            assert_eq!(a,b,"{:?}",kind);
+        }
+    }
+
+    #[test]
+    fn bag_deals_every_piece_once() {
+        let mut bag = Bag::new(42);
+        for _ in 0..20 {
+            let mut seen = deal(&mut bag,7);
+            for piece in PieceKind::ALL {
+                let at = seen.iter().position(|p| *p == piece).expect("piece missing from bag");
+                seen.swap_remove(at);
+            }
+        }
+    }
+
+    #[test]
+    fn tgm_never_starts_with_s_z_or_o() {
+        for seed in 0..200 {
+            let first = Tgm::new(seed,6).next_piece();
+            assert!(![PieceKind::S,PieceKind::Z,PieceKind::O].contains(&first));
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! guideline scoring
+//! see <https://tetris.wiki/Scoring>
+
+/// points per row for soft drops
+pub const SOFT_DROP: u64 = 1;
+/// points per row for hard drops
+pub const HARD_DROP: u64 = 2;
+/// points per step of a combo, multiplied by the level
+pub const COMBO: u64 = 50;
+
+/// kind of T-spin a piece locked with
+#[derive(Debug,Clone,Copy,PartialEq,Default)]
+pub enum TSpin{
+    #[default]
+    None,
+    Mini,
+    Full,
+}
+
+/// points for clearing `lines` rows with one piece at `level`
+/// T-spins score even without clearing anything
+pub fn line_clear(lines: usize, spin: TSpin, level: u32) -> u64{
+    let base = match (spin, lines) {
+        (TSpin::None, 0) => 0,
+        (TSpin::None, 1) => 100,
+        (TSpin::None, 2) => 300,
+        (TSpin::None, 3) => 500,
+        (TSpin::None, _) => 800,
+        (TSpin::Mini, 0) => 100,
+        (TSpin::Mini, 1) => 200,
+        (TSpin::Mini, _) => 400,
+        (TSpin::Full, 0) => 400,
+        (TSpin::Full, 1) => 800,
+        (TSpin::Full, 2) => 1200,
+        (TSpin::Full, _) => 1600,
+    };
+    base * level as u64
+}
+
+/// clears that keep a back-to-back chain going, tetrises and T-spins that clear rows
+pub fn is_difficult(lines: usize, spin: TSpin) -> bool{
+    (lines >= 4) || ((lines > 0) && (spin != TSpin::None))
+}
+
+/// points for a difficult clear following another one, 1.5 times the clear
+pub fn back_to_back(points: u64) -> u64{
+    points * 3 / 2
+}
+
+/// bonus for the `combo`th clearing lock in a row, the first clear is combo 0
+pub fn combo(combo: u32, level: u32) -> u64{
+    COMBO * combo as u64 * level as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_multiplies_clears() {
+        assert_eq!(line_clear(0,TSpin::None,5),0);
+        assert_eq!(line_clear(1,TSpin::None,1),100);
+        assert_eq!(line_clear(3,TSpin::None,2),1000);
+        assert_eq!(line_clear(4,TSpin::None,10),8000);
+        assert_eq!(line_clear(0,TSpin::Mini,2),200);
+        assert_eq!(line_clear(2,TSpin::Full,3),3600);
+    }
+
+    #[test]
+    fn bonuses() {
+        assert!(is_difficult(4,TSpin::None));
+        assert!(is_difficult(1,TSpin::Mini));
+        assert!(!is_difficult(3,TSpin::None));
+        assert!(!is_difficult(0,TSpin::Full));
+        assert_eq!(back_to_back(800),1200);
+        assert_eq!(combo(0,3),0);
+        assert_eq!(combo(4,2),400);
+    }
+}
@@ -0,0 +1,85 @@
+//! super rotation system wall kick data
+//! see <https://tetris.wiki/Super_Rotation_System>
+
+use crate::tetromino::Rotation;
+
+/// which set of kicks a piece uses
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum KickTable{
+    /// J, L, S, T and Z
+    JLSTZ,
+    I,
+    /// the O piece never kicks
+    None,
+}
+
+// offsets are listed as they are on the wiki where +y is up
+// rows are in the order 0->R, R->0, R->2, 2->R, 2->L, L->2, L->0, 0->L
+const JLSTZ_KICKS: [[(i8,i8);5];8] = [
+    [(0,0),(-1,0),(-1, 1),(0,-2),(-1,-2)],
+    [(0,0),( 1,0),( 1,-1),(0, 2),( 1, 2)],
+    [(0,0),( 1,0),( 1,-1),(0, 2),( 1, 2)],
+    [(0,0),(-1,0),(-1, 1),(0,-2),(-1,-2)],
+    [(0,0),( 1,0),( 1, 1),(0,-2),( 1,-2)],
+    [(0,0),(-1,0),(-1,-1),(0, 2),(-1, 2)],
+    [(0,0),(-1,0),(-1,-1),(0, 2),(-1, 2)],
+    [(0,0),( 1,0),( 1, 1),(0,-2),( 1,-2)],
+];
+
+const I_KICKS: [[(i8,i8);5];8] = [
+    [(0,0),(-2,0),( 1,0),(-2,-1),( 1, 2)],
+    [(0,0),( 2,0),(-1,0),( 2, 1),(-1,-2)],
+    [(0,0),(-1,0),( 2,0),(-1, 2),( 2,-1)],
+    [(0,0),( 1,0),(-2,0),( 1,-2),(-2, 1)],
+    [(0,0),( 2,0),(-1,0),( 2, 1),(-1,-2)],
+    [(0,0),(-2,0),( 1,0),(-2,-1),( 1, 2)],
+    [(0,0),( 1,0),(-2,0),( 1,-2),(-2, 1)],
+    [(0,0),(-1,0),( 2,0),(-1, 2),( 2,-1)],
+];
+
+fn transition(from: Rotation, to: Rotation) -> Option<usize> {
+    use Rotation::*;
+    let row = match (from,to) {
+        (Spawn,Right) => 0,
+        (Right,Spawn) => 1,
+        (Right,Reverse) => 2,
+        (Reverse,Right) => 3,
+        (Reverse,Left) => 4,
+        (Left,Reverse) => 5,
+        (Left,Spawn) => 6,
+        (Spawn,Left) => 7,
+        _ => return None,
+    };
+    Some(row)
+}
+
+/// offsets to test in order when rotating from `from` to `to`
+/// offsets are in board co-ords where +y is down
+/// the first offset is always `(0,0)`
+pub fn kicks(table: KickTable, from: Rotation, to: Rotation) -> impl Iterator<Item = (i8,i8)> {
+    let kicks: &'static [(i8,i8)] = match (table, transition(from,to)) {
+        (KickTable::JLSTZ, Some(row)) => &JLSTZ_KICKS[row],
+        (KickTable::I, Some(row)) => &I_KICKS[row],
+        _ => &[(0,0)],
+    };
+
+    kicks.iter().map(|&(x,y)| (x,-y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opposite_transitions_are_inverse() {
+        use Rotation::*;
+        for table in [KickTable::JLSTZ,KickTable::I] {
+            for from in [Spawn,Right,Reverse,Left] {
+                let to = from.cw();
+                for (a,b) in kicks(table,from,to).zip(kicks(table,to,from)) {
+                    assert_eq!(a,(-b.0,-b.1));
+                }
+            }
+        }
+    }
+}
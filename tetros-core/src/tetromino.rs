@@ -0,0 +1,504 @@
+use crate::board::{Board, BlockColour};
+use crate::srs::{self, KickTable};
+use crate::scoring::TSpin;
+
+/// the seven tetrominos
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum PieceKind{
+    I,
+    O,
+    T,
+    S,
+    Z,
+    J,
+    L,
+}
+
+impl PieceKind{
+    pub const ALL: [PieceKind;7] = [
+        PieceKind::I,
+        PieceKind::O,
+        PieceKind::T,
+        PieceKind::S,
+        PieceKind::Z,
+        PieceKind::J,
+        PieceKind::L,
+    ];
+}
+
+impl PieceKind{
+    /// shape and colour of the piece
+    pub fn def(self) -> &'static PieceDef{
+        &PIECES[self as usize]
+    }
+
+    /// the letter the piece is named after
+    pub fn letter(self) -> char{
+        match self {
+            PieceKind::I => 'I',
+            PieceKind::O => 'O',
+            PieceKind::T => 'T',
+            PieceKind::S => 'S',
+            PieceKind::Z => 'Z',
+            PieceKind::J => 'J',
+            PieceKind::L => 'L',
+        }
+    }
+
+    /// reverse of [Self::letter], either case
+    pub fn from_letter(letter: char) -> Option<Self>{
+        Self::ALL.into_iter().find(|kind| kind.letter() == letter.to_ascii_uppercase())
+    }
+}
+
+/// everything fixed about a kind of piece
+#[derive(Debug)]
+pub struct PieceDef{
+    pub kind: PieceKind,
+    pub colour: BlockColour,
+    /// width and height of the bounding box pieces rotate in
+    pub size: usize,
+    /// left of the bounding box when spawned on a 10 wide board
+    pub spawn_column: isize,
+    /// layout of each [Rotation] as a 4x4 mask,
+    /// the lowest bit is the top left and each row is 4 bits
+    pub rotations: [u16;4],
+}
+
+/// builds a 4x4 mask from rows of `#` and `.` separated by spaces
+const fn shape(cells: &[u8]) -> u16{
+    let mut layout = 0;
+    let (mut x,mut y) = (0,0);
+    let mut i = 0;
+    while i < cells.len() {
+        match cells[i] {
+            b'#' => { layout |= 1 << ((y * 4) + x); x += 1 }
+            b'.' => x += 1,
+            _ => { y += 1; x = 0 }
+        }
+        i += 1;
+    }
+    layout
+}
+
+/// guideline pieces in the order of [PieceKind], rotation states are from SRS
+/// see <https://tetris.wiki/Super_Rotation_System>
+const PIECES: [PieceDef;7] = [
+    PieceDef{
+        kind: PieceKind::I,
+        colour: BlockColour::Cyan,
+        size: 4,
+        spawn_column: 3,
+        rotations: [
+            shape(b".... #### .... ...."),
+            shape(b"..#. ..#. ..#. ..#."),
+            shape(b".... .... #### ...."),
+            shape(b".#.. .#.. .#.. .#.."),
+        ],
+    },
+    PieceDef{
+        kind: PieceKind::O,
+        colour: BlockColour::Yellow,
+        size: 2,
+        spawn_column: 4,
+        rotations: [shape(b"## ##");4],
+    },
+    PieceDef{
+        kind: PieceKind::T,
+        colour: BlockColour::Purple,
+        size: 3,
+        spawn_column: 3,
+        rotations: [
+            shape(b".#. ### ..."),
+            shape(b".#. .## .#."),
+            shape(b"... ### .#."),
+            shape(b".#. ##. .#."),
+        ],
+    },
+    PieceDef{
+        kind: PieceKind::S,
+        colour: BlockColour::Green,
+        size: 3,
+        spawn_column: 3,
+        rotations: [
+            shape(b".## ##. ..."),
+            shape(b".#. .## ..#"),
+            shape(b"... .## ##."),
+            shape(b"#.. ##. .#."),
+        ],
+    },
+    PieceDef{
+        kind: PieceKind::Z,
+        colour: BlockColour::Red,
+        size: 3,
+        spawn_column: 3,
+        rotations: [
+            shape(b"##. .## ..."),
+            shape(b"..# .## .#."),
+            shape(b"... ##. .##"),
+            shape(b".#. ##. #.."),
+        ],
+    },
+    PieceDef{
+        kind: PieceKind::J,
+        colour: BlockColour::Blue,
+        size: 3,
+        spawn_column: 3,
+        rotations: [
+            shape(b"#.. ### ..."),
+            shape(b".## .#. .#."),
+            shape(b"... ### ..#"),
+            shape(b".#. .#. ##."),
+        ],
+    },
+    PieceDef{
+        kind: PieceKind::L,
+        colour: BlockColour::Orange,
+        size: 3,
+        spawn_column: 3,
+        rotations: [
+            shape(b"..# ### ..."),
+            shape(b".#. .#. .##"),
+            shape(b"... ### #.."),
+            shape(b"##. .#. .#."),
+        ],
+    },
+];
+
+/// orientation of a tetromino relative to how it spawned
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Rotation{
+    /// "0" state
+    Spawn,
+    /// "R" state, one turn clockwise from spawn
+    Right,
+    /// "2" state
+    Reverse,
+    /// "L" state, one turn anticlockwise from spawn
+    Left,
+}
+
+impl Rotation{
+    /// next state clockwise
+    pub fn cw(self) -> Self{
+        match self {
+            Rotation::Spawn => Rotation::Right,
+            Rotation::Right => Rotation::Reverse,
+            Rotation::Reverse => Rotation::Left,
+            Rotation::Left => Rotation::Spawn,
+        }
+    }
+
+    /// next state anticlockwise
+    pub fn ccw(self) -> Self{
+        match self {
+            Rotation::Spawn => Rotation::Left,
+            Rotation::Left => Rotation::Reverse,
+            Rotation::Reverse => Rotation::Right,
+            Rotation::Right => Rotation::Spawn,
+        }
+    }
+
+    /// position in [PieceDef::rotations]
+    pub fn index(self) -> usize{
+        self as usize
+    }
+}
+
+/// a piece on or above the board
+/// it is a few bytes and never allocates, so it can be copied freely
+#[derive(Clone,Copy)]
+pub struct Tetromino {
+    kind: PieceKind,
+    pub location: (isize,isize),
+    pub colour: BlockColour,
+    rotation: Rotation,
+    last_kick: Option<usize>, //kick used by the last rotation, cleared by any other movement
+    mask: u16, //current layout from [PieceDef::rotations]
+}
+
+impl Tetromino{
+    /// builds the tetromino for `kind` in its spawn orientation
+    /// `location` is (0,0), see [PieceDef::spawn_column] for where it should spawn
+    pub fn from_kind(kind: PieceKind) -> Self{
+        let def = kind.def();
+        Self{
+            kind,
+            location: (0,0),
+            colour: def.colour,
+            rotation: Rotation::Spawn,
+            last_kick: None,
+            mask: def.rotations[Rotation::Spawn.index()],
+        }
+    }
+
+    pub fn kind(&self) -> PieceKind {
+        self.kind
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// kicks are chosen by the piece
+    pub fn kick_table(&self) -> KickTable {
+        match self.kind {
+            PieceKind::I => KickTable::I,
+            PieceKind::O => KickTable::None,
+            _ => KickTable::JLSTZ,
+        }
+    }
+
+    /// board co-ords of every block in self
+    pub fn blocks(&self) -> impl Iterator<Item = (isize,isize)> {
+        let (mask,(x,y)) = (self.mask,self.location);
+        (0..16).filter(move |i| mask & (1 << i) != 0).map(move |i| (x + (i % 4), y + (i / 4)))
+    }
+
+    /// layout of the current rotation, see [PieceDef::rotations]
+    pub fn mask(&self) -> u16 {
+        self.mask
+    }
+
+    /// changes orientation without checking the board
+    fn set_rotation(&mut self, rotation: Rotation){
+        self.rotation = rotation;
+        self.mask = self.kind.def().rotations[rotation.index()];
+    }
+
+    pub fn rotate_right(&mut self){
+        self.set_rotation(self.rotation.cw());
+    }
+
+    pub fn rotate_left(&mut self){
+        self.set_rotation(self.rotation.ccw());
+    }
+
+    /// locks self into `board`
+    pub fn set(&self, board: &mut Board){
+        for (x,y) in self.blocks(){
+            if x.is_negative() || y.is_negative() { continue }
+
+            board.set((x as usize,y as usize),self.colour);
+        }
+    }
+
+    pub fn relocate(&mut self, to: (i8,i8)) {
+        let (x,y) = to;
+
+        self.location.0 += x as isize;
+        self.location.1 += y as isize;
+    }
+
+    /// check for occupied spaces around tetromino
+    /// returns true if self can stay here
+    pub fn is_legal(&self, board: &Board) -> bool{
+        self.is_legal_at(board,(0,0))
+    }
+
+    /// returns true if self could be moved by `offset`
+    /// neither self or `board` are changed
+    pub fn is_legal_at(&self, board: &Board, offset: (isize,isize)) -> bool{
+        !board.collides(self.mask,(self.location.0 + offset.0, self.location.1 + offset.1))
+    }
+
+    /// number of rows self can fall before landing
+    pub fn drop_distance(&self, board: &Board) -> isize{
+        let mut rows = 0;
+        while self.is_legal_at(board,(0,rows + 1)) {
+            rows += 1;
+        }
+        rows
+    }
+
+    pub fn legal_move(&mut self, to: (i8,i8),board: &Board) -> bool{
+        self.relocate(to);
+        if !self.is_legal(board){
+            let (x,y) = to;
+            self.relocate((-x,-y));
+            return false
+        }
+        if to != (0,0) {
+            self.last_kick = None;
+        }
+        true
+    }
+
+    /// moves self down `rows` without checking the board, use [Self::drop_distance] first
+    pub fn fall(&mut self, rows: isize){
+        if rows == 0 { return }
+        self.location.1 += rows;
+        self.last_kick = None;
+    }
+
+    /// kick index of the last rotation if that was the last thing to move self
+    pub fn last_kick(&self) -> Option<usize> {
+        self.last_kick
+    }
+
+    /// checks for a T-spin using the 3 corner rule
+    /// at least 3 of the corners around the centre of the T must be filled
+    /// and the last movement must have been a rotation,
+    /// it is a mini unless both corners beside the point of the T are filled or the last kick was used
+    pub fn t_spin(&self, board: &Board) -> TSpin{
+        let kick = match self.last_kick {
+            Some(kick) if self.kind == PieceKind::T => kick,
+            _ => return TSpin::None,
+        };
+
+        let (cx,cy) = (self.location.0 + 1, self.location.1 + 1);
+        let filled = |x: isize, y: isize| !board.is_free((cx + x, cy + y));
+        let corners = [(-1,-1),(1,-1),(-1,1),(1,1)].iter().filter(|(x,y)| filled(*x,*y)).count();
+        if corners < 3 { return TSpin::None }
+
+        //the flat side of the T is the one neighbour of the centre not in the piece
+        let back = [(0,-1),(1,0),(0,1),(-1,0)].into_iter()
+            .find(|(x,y)| !self.blocks().any(|b| b == (cx + x, cy + y)));
+        let (bx,by) = match back {
+            Some(b) => b,
+            None => return TSpin::None,
+        };
+        //corners either side of the point, opposite the flat side
+        let front = [(-bx - by, -by + bx),(-bx + by, -by - bx)];
+        let front_filled = front.iter().filter(|(x,y)| filled(*x,*y)).count();
+
+        if (front_filled == 2) || (kick == 4) {
+            TSpin::Full
+        } else {
+            TSpin::Mini
+        }
+    }
+
+    /// rotates using the SRS wall kicks
+    /// returns the index of the kick that was used, 0 is the unkicked rotation
+    /// if every kick fails self is left unchanged and `None` is returned
+    pub fn srs_rotate(&mut self, clockwise: bool, board: &Board) -> Option<usize>{
+        let from = self.rotation;
+        if clockwise { self.rotate_right() } else { self.rotate_left() }
+
+        for (i,kick) in srs::kicks(self.kick_table(),from,self.rotation).enumerate(){
+            if self.legal_move(kick,board){
+                self.last_kick = Some(i);
+                return Some(i)
+            }
+        }
+
+        if clockwise { self.rotate_left() } else { self.rotate_right() }
+        None
+    }
+
+    pub fn safe_ror(&mut self, board: &Board) -> bool{
+        self.srs_rotate(true,board).is_some()
+    }
+    pub fn safe_rol(&mut self, board: &Board) -> bool{
+        self.srs_rotate(false,board).is_some()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    /// turns the top left `size` by `size` of a mask a quarter clockwise
+    fn turn(layout: u16, size: usize) -> u16 {
+        let mut turned = 0;
+        for y in 0..size {
+            for x in 0..size {
+                if layout & (1 << ((y * 4) + x)) != 0 {
+                    turned |= 1 << ((x * 4) + (size - 1 - y));
+                }
+            }
+        }
+        turned
+    }
+
+    #[test]
+    fn every_shape_has_four_cells() {
+        for kind in PieceKind::ALL {
+            let def = kind.def();
+            assert_eq!(def.kind,kind);
+            for layout in def.rotations {
+                assert_eq!(layout.count_ones(),4,"{:?}",kind);
+                //nothing outside of the bounding box
+                assert_eq!(turn(turn(turn(turn(layout,def.size),def.size),def.size),def.size),layout,"{:?}",kind);
+            }
+        }
+    }
+
+    #[test]
+    fn rotation_states_are_quarter_turns() {
+        for kind in PieceKind::ALL {
+            let def = kind.def();
+            let mut rotation = Rotation::Spawn;
+            for _ in 0..4 {
+                let next = rotation.cw();
+                assert_eq!(turn(def.rotations[rotation.index()],def.size),def.rotations[next.index()],"{:?} {:?}",kind,rotation);
+                assert_eq!(next.ccw(),rotation);
+                rotation = next;
+            }
+            assert_eq!(rotation,Rotation::Spawn);
+        }
+    }
+
+    #[test]
+    fn four_rotations_return_to_start() {
+        let start = Tetromino::from_kind(PieceKind::T);
+        let mut tet = start;
+        for _ in 0..4 {
+            tet.rotate_right();
+        }
+        assert_eq!(tet.mask(),start.mask());
+
+        tet.rotate_left();
+        tet.rotate_right();
+        assert_eq!(tet.mask(),start.mask());
+        assert_eq!(tet.rotation(),Rotation::Spawn);
+    }
+
+    #[test]
+    fn i_piece_kicks_off_the_wall() {
+        let board = Board::new(Board::GAME_WIDTH,Board::GAME_HEIGHT,0);
+        let mut tet = Tetromino::from_kind(PieceKind::I);
+        tet.location = (0,4);
+        assert!(tet.safe_rol(&board));
+
+        //vertical in the left most column
+        tet.location = (-1,4);
+        assert!(tet.is_legal(&board));
+
+        //the unkicked rotation would leave the board, L->0 tests (+1,0) second
+        assert_eq!(tet.srs_rotate(true,&board),Some(1));
+        assert_eq!(tet.rotation(),Rotation::Spawn);
+        assert_eq!(tet.location,(0,4));
+    }
+
+    #[test]
+    fn failed_rotation_leaves_piece_alone() {
+        let mut board = Board::new(3,3,0);
+        for x in 0..3 {
+            board.set((x,2),BlockColour::Grey);
+        }
+        let mut tet = Tetromino::from_kind(PieceKind::S);
+        let blocks: Vec<_> = tet.blocks().collect();
+
+        assert_eq!(tet.srs_rotate(true,&board),None);
+        assert_eq!(tet.rotation(),Rotation::Spawn);
+        assert_eq!(tet.blocks().collect::<Vec<_>>(),blocks);
+    }
+
+    #[test]
+    fn walls_block_movement() {
+        let board = Board::new(Board::GAME_WIDTH,Board::GAME_HEIGHT,0);
+        let mut tet = Tetromino::from_kind(PieceKind::O);
+
+        assert!(!tet.legal_move((-1,0),&board));
+        assert_eq!(tet.location,(0,0));
+
+        for _ in 0..Board::GAME_WIDTH - 2 {
+            assert!(tet.legal_move((1,0),&board));
+        }
+        assert!(!tet.legal_move((1,0),&board));
+    }
+}